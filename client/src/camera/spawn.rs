@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use bevy_atmosphere::prelude::AtmosphereCamera;
 use bevy_mod_raycast::prelude::*;
 
+use crate::player::{CurrentPlayerMarker, Player};
 use crate::GameState;
 
 #[derive(TypePath)]
@@ -26,7 +27,17 @@ impl Default for CameraController {
     }
 }
 
-pub fn spawn_camera(mut commands: Commands) {
+pub fn spawn_camera(
+    mut commands: Commands,
+    player_query: Query<&Player, With<CurrentPlayerMarker>>,
+) {
+    // The camera looks where the player was looking when it left the world
+    let mut controller = CameraController::default();
+    if let Ok(player) = player_query.get_single() {
+        controller.angle_x = player.orientation.yaw;
+        controller.angle_y = -player.orientation.pitch;
+    }
+
     commands
         .spawn(Camera3dBundle {
             transform: Transform::from_translation(Vec3::new(0.0, 5.0, 10.0))
@@ -37,7 +48,7 @@ pub fn spawn_camera(mut commands: Commands) {
             }),
             ..Default::default()
         })
-        .insert(CameraController::default()) // Ajoute le CameraController
+        .insert(controller) // Ajoute le CameraController
         .insert({
             let mut raycast_source = RaycastSource::<BlockRaycastSet>::default(); // Initialisation par défaut
             raycast_source.cast_method = RaycastMethod::Transform; // Utilise la transformation de la caméra pour lancer le rayon
@@ -4,7 +4,7 @@ use crate::ui::hud::chat::{render_chat, setup_chat};
 use bevy::prelude::*;
 use bevy_atmosphere::prelude::*;
 use inventory::Inventory;
-use shared::messages::PlayerSpawnEvent;
+use shared::messages::{PlayerDespawnEvent, PlayerSpawnEvent};
 
 use crate::world::time::ClientTime;
 use crate::world::ClientWorldMap;
@@ -32,12 +32,13 @@ use crate::player::*;
 use crate::ui::hud::inventory::*;
 use shared::world::{BlockId, ItemId, WorldSeed};
 
-use crate::menus::loading::load_loading_screen;
+use crate::menus::loading::{display_connection_error, load_loading_screen};
 use crate::network::{
     establish_authenticated_connection_to_server, init_server_connection,
-    launch_local_server_system, network_failure_handler, poll_network_messages,
-    send_player_position_to_server, terminate_server_connection, upload_player_inputs_system,
-    CurrentPlayerProfile, TargetServer, TargetServerState,
+    launch_local_server_system, network_failure_handler, poll_connect_token_request,
+    poll_network_messages, terminate_server_connection, update_cached_chat_state,
+    update_world_from_network, upload_player_inputs_system, CurrentPlayerProfile, TargetServer,
+    TargetServerState,
 };
 use crate::{DisplayQuality, GameState, Volume};
 
@@ -91,6 +92,7 @@ pub fn game_plugin(app: &mut App) {
         .insert_resource(CurrentPlayerProfile::new())
         .add_event::<WorldRenderRequestUpdateEvent>()
         .add_event::<PlayerSpawnEvent>()
+        .add_event::<PlayerDespawnEvent>()
         .add_systems(
             OnEnter(GameState::PreGameLoading),
             (
@@ -104,10 +106,24 @@ pub fn game_plugin(app: &mut App) {
         .add_systems(
             Update,
             (
-                establish_authenticated_connection_to_server,
+                poll_network_messages,
+                update_cached_chat_state.after(poll_network_messages),
+            )
+                .run_if(in_state(GameState::PreGameLoading).or_else(in_state(GameState::Game))),
+        )
+        .add_systems(
+            Update,
+            (
+                (
+                    poll_connect_token_request,
+                    establish_authenticated_connection_to_server,
+                    display_connection_error,
+                )
+                    .chain()
+                    .after(poll_network_messages),
                 create_all_atlases,
                 check_pre_loading_complete,
-                spawn_player,
+                (spawn_player, despawn_player).chain(),
             )
                 .run_if(in_state(GameState::PreGameLoading)),
         )
@@ -168,11 +184,16 @@ pub fn game_plugin(app: &mut App) {
         .add_systems(
             Update,
             (
-                poll_network_messages,
                 network_failure_handler,
-                upload_player_inputs_system,
-                send_player_position_to_server,
-                spawn_player,
+                (
+                    update_world_from_network,
+                    upload_player_inputs_system,
+                    interpolate_remote_players,
+                )
+                    .chain()
+                    .after(poll_network_messages),
+                inventory::apply_inventory_updates.after(poll_network_messages),
+                (spawn_player, despawn_player).chain(),
             )
                 .run_if(in_state(GameState::Game)),
         )
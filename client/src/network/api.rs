@@ -1,7 +1,8 @@
 use bevy::{math::IVec3, prelude::ResMut};
 use bevy_renet::renet::{DefaultChannel, RenetClient};
 use bincode::Options;
-use shared::messages::{ChatMessage, ClientToServerMessage, SaveWorldRequest};
+use shared::messages::{ChatMessageRequest, ClientToServerMessage, SaveWorldRequest};
+use shared::players::InventoryAction;
 use shared::world::BlockData;
 
 pub enum NetworkAction {
@@ -15,21 +16,18 @@ pub enum NetworkAction {
     BlockInteraction {
         position: IVec3,
         block_type: Option<BlockData>, // None = suppression, Some = ajout
+        slot: u32,
     },
+    InventoryAction(InventoryAction),
 }
 
 pub fn send_network_action(client: &mut ResMut<RenetClient>, action: NetworkAction) {
     match action {
         NetworkAction::ChatMessage(msg) => {
-            let timestamp_ms = std::time::SystemTime::now()
-                .duration_since(std::time::UNIX_EPOCH)
-                .unwrap()
-                .as_millis() as u64;
+            // The server knows who is writing, and when
             let input_message = bincode::options()
-                .serialize(&ClientToServerMessage::ChatMessage(ChatMessage {
-                    author_name: "User".into(),
+                .serialize(&ClientToServerMessage::ChatMessage(ChatMessageRequest {
                     content: msg,
-                    date: timestamp_ms,
                 }))
                 .unwrap();
 
@@ -63,15 +61,24 @@ pub fn send_network_action(client: &mut ResMut<RenetClient>, action: NetworkActi
         NetworkAction::BlockInteraction {
             position,
             block_type,
+            slot,
         } => {
             let message = bincode::options()
                 .serialize(&ClientToServerMessage::BlockInteraction {
                     position,
                     block_type,
+                    slot,
                 })
                 .unwrap();
 
             client.send_message(DefaultChannel::ReliableOrdered, message);
         }
+        NetworkAction::InventoryAction(action) => {
+            let message = bincode::options()
+                .serialize(&ClientToServerMessage::InventoryAction(action))
+                .unwrap();
+
+            client.send_message(DefaultChannel::ReliableOrdered, message);
+        }
     }
 }
@@ -1,12 +1,16 @@
 use crate::network::api::{send_network_action, NetworkAction};
 use bevy::prelude::*;
 use bevy_renet::renet::RenetClient;
-use shared::messages::ChatConversation;
+use shared::messages::{ChatConversation, ChatMessageId};
+
+use super::ChatMessagesEvent;
 
 #[derive(Resource, Default, Debug)]
 pub struct CachedChatConversation {
     pub last_update: u64,
     pub data: Option<ChatConversation>,
+    /// Id of the last message displayed by the chat
+    pub last_rendered_id: Option<ChatMessageId>,
 }
 
 pub fn send_chat_message(client: &mut ResMut<RenetClient>, msg: &str) {
@@ -14,16 +18,29 @@ pub fn send_chat_message(client: &mut ResMut<RenetClient>, msg: &str) {
 }
 
 pub fn update_cached_chat_state(
-    chat_state: &mut ResMut<CachedChatConversation>,
-    new_state: ChatConversation,
+    mut chat_state: ResMut<CachedChatConversation>,
+    mut ev_chat: EventReader<ChatMessagesEvent>,
 ) {
+    if ev_chat.is_empty() {
+        return;
+    }
+
+    // The history replayed when joining can contain messages already received
+    let conversation = chat_state
+        .data
+        .get_or_insert_with(ChatConversation::default);
+    for ChatMessagesEvent(messages) in ev_chat.read() {
+        for message in messages {
+            conversation.push(message.clone());
+        }
+    }
+
     let now = std::time::SystemTime::now()
         .duration_since(std::time::UNIX_EPOCH)
         .unwrap()
         .as_millis() as u64;
 
     chat_state.last_update = now;
-    chat_state.data = Some(new_state);
 
     trace!("new CachedChatConversation: {:?}", &chat_state);
 }
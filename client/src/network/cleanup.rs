@@ -19,4 +19,6 @@ pub fn terminate_server_connection(
     target.username = None;
     target.session_token = None;
     target.state = TargetServerState::Initial;
+    target.is_solo = false;
+    target.unsecure = false;
 }
@@ -0,0 +1,110 @@
+use bevy::prelude::*;
+use bevy_renet::renet::{DefaultChannel, RenetClient};
+use bincode::Options;
+use shared::messages::{
+    AuthRegisterResponse, BlockChanges, ChatMessage, HandshakeResponse, PlayerDespawnEvent,
+    PlayerSpawnEvent, ServerToClientMessage, WorldUpdate,
+};
+use shared::players::Inventory;
+
+#[derive(Event, Debug, Clone)]
+pub struct HandshakeResponseEvent(pub HandshakeResponse);
+
+#[derive(Event, Debug, Clone)]
+pub struct AuthRegisterResponseEvent(pub AuthRegisterResponse);
+
+/// New messages of the conversation, or the history replayed when joining
+#[derive(Event, Debug, Clone)]
+pub struct ChatMessagesEvent(pub Vec<ChatMessage>);
+
+/// Inventory of the player, as the server knows it
+#[derive(Event, Debug, Clone)]
+pub struct InventoryUpdateEvent(pub Inventory);
+
+#[derive(Event, Debug, Clone)]
+pub struct WorldUpdateEvent(pub WorldUpdate);
+
+#[derive(Event, Debug, Clone)]
+pub struct BlockChangesEvent(pub BlockChanges);
+
+/// Sent when a message of the server cannot be read, usually because its version is different
+#[derive(Event, Debug, Clone)]
+pub struct UnreadableMessageEvent {
+    pub error: String,
+}
+
+pub fn add_network_events(app: &mut App) {
+    app.add_event::<HandshakeResponseEvent>()
+        .add_event::<AuthRegisterResponseEvent>()
+        .add_event::<ChatMessagesEvent>()
+        .add_event::<InventoryUpdateEvent>()
+        .add_event::<WorldUpdateEvent>()
+        .add_event::<BlockChangesEvent>()
+        .add_event::<UnreadableMessageEvent>();
+}
+
+/// Reads the messages of every channel, and sends each of them as the matching event\
+/// Every payload sent by the server is a `ServerToClientMessage`
+pub fn poll_network_messages(
+    mut client: ResMut<RenetClient>,
+    mut ev_handshake: EventWriter<HandshakeResponseEvent>,
+    mut ev_auth: EventWriter<AuthRegisterResponseEvent>,
+    mut ev_chat: EventWriter<ChatMessagesEvent>,
+    mut ev_world_update: EventWriter<WorldUpdateEvent>,
+    mut ev_block_changes: EventWriter<BlockChangesEvent>,
+    mut ev_spawn: EventWriter<PlayerSpawnEvent>,
+    mut ev_despawn: EventWriter<PlayerDespawnEvent>,
+    mut ev_inventory_update: EventWriter<InventoryUpdateEvent>,
+    mut ev_unreadable: EventWriter<UnreadableMessageEvent>,
+) {
+    for channel in [
+        DefaultChannel::ReliableOrdered,
+        DefaultChannel::ReliableUnordered,
+        DefaultChannel::Unreliable,
+    ] {
+        while let Some(bytes) = client.receive_message(channel) {
+            let msg = match bincode::options().deserialize::<ServerToClientMessage>(&bytes) {
+                Ok(msg) => msg,
+                Err(e) => {
+                    error!("Failed to parse incoming message: {}", e);
+                    ev_unreadable.send(UnreadableMessageEvent {
+                        error: e.to_string(),
+                    });
+                    continue;
+                }
+            };
+
+            match msg {
+                ServerToClientMessage::Handshake(response) => {
+                    ev_handshake.send(HandshakeResponseEvent(response));
+                }
+                ServerToClientMessage::AuthRegisterResponse(response) => {
+                    ev_auth.send(AuthRegisterResponseEvent(response));
+                }
+                ServerToClientMessage::ChatHistory(conversation) => {
+                    ev_chat.send(ChatMessagesEvent(conversation.messages));
+                }
+                ServerToClientMessage::ChatMessage(message) => {
+                    ev_chat.send(ChatMessagesEvent(vec![message]));
+                }
+                ServerToClientMessage::WorldUpdate(world_update) => {
+                    ev_world_update.send(WorldUpdateEvent(world_update));
+                }
+                ServerToClientMessage::BlockChanges(block_changes) => {
+                    ev_block_changes.send(BlockChangesEvent(block_changes));
+                }
+                ServerToClientMessage::PlayerSpawn(spawn_event) => {
+                    info!("Received SINGLE spawn event {:?}", spawn_event);
+                    ev_spawn.send(spawn_event);
+                }
+                ServerToClientMessage::PlayerDespawn(despawn_event) => {
+                    info!("Received despawn event {:?}", despawn_event);
+                    ev_despawn.send(despawn_event);
+                }
+                ServerToClientMessage::InventoryUpdate(inventory) => {
+                    ev_inventory_update.send(InventoryUpdateEvent(inventory));
+                }
+            }
+        }
+    }
+}
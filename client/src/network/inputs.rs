@@ -1,45 +1,147 @@
+use crate::camera::CameraController;
 use crate::input::data::GameAction;
-use crate::input::keyboard::is_action_pressed;
+use crate::input::keyboard::{is_action_just_pressed, is_action_pressed};
+use crate::player::{CurrentPlayerMarker, Player};
+use crate::ui::hud::UIMode;
+use crate::world::{ClientWorldMap, FirstChunkReceived};
 use crate::KeyMap;
 use bevy::input::ButtonInput;
 use bevy::prelude::*;
 use bevy_renet::renet::{DefaultChannel, RenetClient};
 use bincode::Options;
 use shared::messages::{ClientToServerMessage, NetworkPlayerInput, PlayerInputs};
+use shared::players::simulate_player_movement;
+use std::collections::VecDeque;
 
+use super::world::LastWorldUpdate;
+
+/// Maximum number of inputs kept while waiting for the server to acknowledge them
+const MAX_PENDING_INPUTS: usize = 1024;
+
+/// Inputs sent to the server, used to predict the movements of the current player\
+/// Reset for each connection
+#[derive(Resource, Default)]
+pub struct PendingInputs {
+    last_tick: u64,
+    inputs: VecDeque<PlayerInputs>,
+}
+
+impl PendingInputs {
+    fn next_tick(&mut self) -> u64 {
+        self.last_tick += 1;
+        self.last_tick
+    }
+
+    fn push(&mut self, inputs: PlayerInputs) {
+        if self.inputs.len() >= MAX_PENDING_INPUTS {
+            self.inputs.pop_front();
+        }
+        self.inputs.push_back(inputs);
+    }
+
+    /// Forgets the inputs already applied by the server
+    fn acknowledge(&mut self, tick: u64) {
+        while self
+            .inputs
+            .front()
+            .is_some_and(|inputs| inputs.tick <= tick)
+        {
+            self.inputs.pop_front();
+        }
+    }
+}
+
+/// Sends the inputs of the current frame to the server, and applies them locally without waiting for its answer\
+/// When the server position for an acknowledged tick is received, the inputs sent since are replayed over it
 pub fn upload_player_inputs_system(
     mut client: ResMut<RenetClient>,
     keyboard_input: Res<ButtonInput<KeyCode>>,
     key_map: Res<KeyMap>,
+    ui_mode: Res<UIMode>,
+    time: Res<Time>,
+    camera: Query<&Transform, (With<Camera>, With<CameraController>, Without<Player>)>,
+    mut player_query: Query<(&mut Transform, &mut Player), With<CurrentPlayerMarker>>,
+    world_map: Res<ClientWorldMap>,
+    first_chunk_received: Res<FirstChunkReceived>,
+    mut pending_inputs: ResMut<PendingInputs>,
+    mut last_update: ResMut<LastWorldUpdate>,
 ) {
-    let mut actions: Vec<NetworkPlayerInput> = vec![];
-    if is_action_pressed(GameAction::MoveBackward, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::Backward)
-    }
-    if is_action_pressed(GameAction::MoveForward, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::Forward)
-    }
-    if is_action_pressed(GameAction::MoveLeft, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::Left)
-    }
-    if is_action_pressed(GameAction::MoveRight, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::Right)
-    }
-    if is_action_pressed(GameAction::ToggleFlyMode, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::ToggleFlyMode)
-    }
-    if is_action_pressed(GameAction::FlyUp, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::FlyUp);
+    let Ok(camera_transform) = camera.get_single() else {
+        return;
+    };
+    let Ok((mut player_transform, mut player)) = player_query.get_single_mut() else {
+        return;
+    };
+
+    // Server reconciliation
+    if let Some((position, state)) = last_update.current_player_state.take() {
+        pending_inputs.acknowledge(state.last_input_tick);
+
+        let mut predicted_position = position;
+        player.apply_movement_state(&state);
+        if first_chunk_received.0 {
+            for inputs in pending_inputs.inputs.iter() {
+                simulate_player_movement(
+                    &mut player,
+                    &mut predicted_position,
+                    &*world_map,
+                    inputs,
+                    inputs.delta,
+                );
+            }
+        }
+        player_transform.translation = predicted_position;
     }
-    if is_action_pressed(GameAction::FlyDown, &keyboard_input, &key_map) {
-        actions.push(NetworkPlayerInput::FlyDown);
+
+    let mut actions: Vec<NetworkPlayerInput> = vec![];
+    if *ui_mode == UIMode::Closed {
+        if is_action_pressed(GameAction::MoveBackward, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::Backward)
+        }
+        if is_action_pressed(GameAction::MoveForward, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::Forward)
+        }
+        if is_action_pressed(GameAction::MoveLeft, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::Left)
+        }
+        if is_action_pressed(GameAction::MoveRight, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::Right)
+        }
+        if is_action_pressed(GameAction::Jump, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::Jump)
+        }
+        // fly mode (f key)
+        if is_action_just_pressed(GameAction::ToggleFlyMode, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::ToggleFlyMode)
+        }
+        if is_action_pressed(GameAction::FlyUp, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::FlyUp);
+        }
+        if is_action_pressed(GameAction::FlyDown, &keyboard_input, &key_map) {
+            actions.push(NetworkPlayerInput::FlyDown);
+        }
     }
 
-    let msg = ClientToServerMessage::PlayerInputs(PlayerInputs {
-        tick: 0,
+    let inputs = PlayerInputs {
+        tick: pending_inputs.next_tick(),
         actions,
-        direction: Vec3::ZERO,
-    });
+        direction: camera_transform.forward().xyz(),
+        delta: time.delta_seconds(),
+    };
+
+    let msg = ClientToServerMessage::PlayerInputs(inputs.clone());
     let payload = bincode::options().serialize(&msg).unwrap();
     client.send_message(DefaultChannel::ReliableOrdered, payload);
+
+    // Client-side prediction
+    if first_chunk_received.0 {
+        simulate_player_movement(
+            &mut player,
+            &mut player_transform.translation,
+            &*world_map,
+            &inputs,
+            inputs.delta,
+        );
+    }
+    pending_inputs.push(inputs);
 }
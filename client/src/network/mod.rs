@@ -1,15 +1,17 @@
 pub mod api;
 mod chat;
 mod cleanup;
+mod dispatcher;
 mod inputs;
-pub mod player;
 pub mod save;
 mod setup;
+mod token;
 mod world;
 
 pub use chat::*;
 pub use cleanup::*;
+pub use dispatcher::*;
 pub use inputs::*;
-pub use player::*;
 pub use setup::*;
-pub use world::request_world_update;
+pub use token::*;
+pub use world::{request_world_update, update_world_from_network};
@@ -4,33 +4,38 @@ use rand::Rng;
 use shared::{get_shared_renet_config, GameServerConfig};
 
 use crate::menus::solo::SelectedWorld;
-use crate::network::world::update_world_from_network;
-use crate::network::{update_cached_chat_state, CachedChatConversation};
-use crate::player::{CurrentPlayerMarker, Player};
-use crate::world::render_distance::RenderDistance;
-use crate::world::time::ClientTime;
-use crate::world::WorldRenderRequestUpdateEvent;
+use crate::network::world::LastWorldUpdate;
+use crate::network::{
+    add_network_events, request_connect_token, AuthRegisterResponseEvent, CachedChatConversation,
+    HandshakeResponseEvent, PendingInputs, UnreadableMessageEvent,
+};
+use crate::player::inventory::Inventory;
+use crate::player::ServerClock;
+use bevy::tasks::{block_on, futures_lite::future, IoTaskPool, Task};
 use bevy_renet::renet::transport::{
-    ClientAuthentication, NetcodeClientTransport, NetcodeTransportError,
+    ClientAuthentication, ConnectToken, NetcodeClientTransport, NetcodeTransportError,
 };
 use bevy_renet::renet::DefaultChannel;
 use bevy_renet::transport::NetcodeClientPlugin;
 use bincode::Options;
 use shared::messages::{
-    AuthRegisterRequest, ChatConversation, ClientToServerMessage, PlayerId, PlayerSpawnEvent,
+    AuthRegisterRequest, AuthRegisterResponse, ClientToServerMessage, HandshakeRequest,
+    HandshakeResponse, PlayerId, PlayerSpawnEvent,
 };
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
 use std::{net::UdpSocket, thread, time::SystemTime};
 
-use crate::world::ClientWorldMap;
 use shared::GameFolderPaths;
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum TargetServerState {
     Initial,
+    RequestingToken, // waiting for the token service, see `ConnectTokenRequest`
+    Handshaking,
     Establising,
     ConnectionEstablished,
     FullyReady, // player has spawned
+    Refused,    // the reason is stored in `ConnectionError`
 }
 
 #[derive(Resource, Clone)]
@@ -56,6 +61,20 @@ pub struct TargetServer {
     pub username: Option<String>,
     pub session_token: Option<u128>,
     pub state: TargetServerState,
+    /// Local server of a solo world, which never needs a connect token
+    pub is_solo: bool,
+    /// Chosen by the player for servers without secure authentication, no connect token is requested
+    pub unsecure: bool,
+}
+
+/// Connect token being requested to the token service of the server, in the background
+#[derive(Resource)]
+pub struct ConnectTokenRequest(Task<Result<ConnectToken, String>>);
+
+/// Reason of the last failed connection, displayed by the loading screen and the multiplayer menu
+#[derive(Resource, Default, Debug)]
+pub struct ConnectionError {
+    pub reason: Option<String>,
 }
 
 pub fn add_base_netcode(app: &mut App) {
@@ -73,7 +92,11 @@ pub fn add_base_netcode(app: &mut App) {
         username: None,
         session_token: None,
         state: TargetServerState::Initial,
+        is_solo: false,
+        unsecure: false,
     });
+    app.init_resource::<ConnectionError>();
+    add_network_events(app);
 }
 
 pub fn launch_local_server_system(
@@ -103,85 +126,89 @@ pub fn launch_local_server_system(
                 GameServerConfig {
                     world_name: world_name_clone,
                     is_solo: true,
+                    secure: None,
                 },
+                // server.ron is only used by dedicated servers
+                server::ServerSettings::default(),
                 game_folder_path,
             );
         });
 
         target.address = Some(addr);
+        target.is_solo = true;
     } else {
         error!("Error: No world selected. Unable to launch the server.");
     }
 }
 
-fn poll_reliable_ordered_messages(
-    client: &mut ResMut<RenetClient>,
-    chat_state: &mut ResMut<CachedChatConversation>,
+pub fn init_server_connection(
+    mut commands: Commands,
+    mut target: ResMut<TargetServer>,
+    current_profile: Res<CurrentPlayerProfile>,
 ) {
-    while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        let message = bincode::options().deserialize::<ChatConversation>(&message);
-        match message {
-            Ok(data) => {
-                update_cached_chat_state(chat_state, data);
-            }
-            Err(e) => error!("err {}", e),
-        };
+    let addr = target.address.unwrap();
+
+    // Solo worlds never need a connect token, unsecure servers do not use them
+    if target.is_solo || target.unsecure {
+        info!("Using unsecure authentication");
+        connect_to_server(&mut commands, addr, None, current_profile.id);
+        return;
     }
-}
 
-fn poll_reliable_unordered_messages(
-    client: &mut ResMut<RenetClient>,
-    world: &mut ResMut<ClientWorldMap>,
-    client_time: ResMut<ClientTime>,
-    ev_render: &mut EventWriter<WorldRenderRequestUpdateEvent>,
-    players: &mut Query<(&mut Transform, &Player), With<Player>>,
-    current_player_entity: Query<Entity, With<CurrentPlayerMarker>>,
-    render_distance: Res<RenderDistance>,
-    ev_spawn: &mut EventWriter<PlayerSpawnEvent>,
-) {
-    update_world_from_network(
-        client,
-        world,
-        client_time,
-        ev_render,
-        players,
-        current_player_entity,
-        render_distance,
-        ev_spawn,
-    );
+    let username = target
+        .username
+        .clone()
+        .unwrap_or_else(|| current_profile.name.clone());
+    // The token service may take a few seconds to answer, the game keeps running meanwhile
+    let task = IoTaskPool::get()
+        .spawn(async move { request_connect_token(addr, &username).map_err(|e| e.to_string()) });
+    commands.insert_resource(ConnectTokenRequest(task));
+    target.state = TargetServerState::RequestingToken;
 }
 
-pub fn poll_network_messages(
+/// Connects to the server once the connect token is received\
+/// Without connect token, the connection fails and the loading screen displays why
+pub fn poll_connect_token_request(
+    mut commands: Commands,
+    request: Option<ResMut<ConnectTokenRequest>>,
     mut client: ResMut<RenetClient>,
-    mut chat_state: ResMut<CachedChatConversation>,
-    client_time: ResMut<ClientTime>,
-    mut world: ResMut<ClientWorldMap>,
-    mut ev_render: EventWriter<WorldRenderRequestUpdateEvent>,
-    mut players: Query<(&mut Transform, &Player), With<Player>>,
-    current_player_entity: Query<Entity, With<CurrentPlayerMarker>>,
-    render_distance: Res<RenderDistance>,
-    mut ev_spawn: EventWriter<PlayerSpawnEvent>,
+    mut target: ResMut<TargetServer>,
+    mut current_profile: ResMut<CurrentPlayerProfile>,
+    mut connection_error: ResMut<ConnectionError>,
 ) {
-    poll_reliable_ordered_messages(&mut client, &mut chat_state);
-    poll_reliable_unordered_messages(
-        &mut client,
-        &mut world,
-        client_time,
-        &mut ev_render,
-        &mut players,
-        current_player_entity,
-        render_distance,
-        &mut ev_spawn,
-    );
+    let Some(mut request) = request else {
+        return;
+    };
+    let Some(result) = block_on(future::poll_once(&mut request.0)) else {
+        return;
+    };
+    commands.remove_resource::<ConnectTokenRequest>();
+
+    match result {
+        Ok(token) => {
+            info!("Connect token received, using secure authentication");
+            // The id is chosen by the token service
+            current_profile.id = token.client_id;
+            let id = token.client_id;
+            connect_to_server(&mut commands, target.address.unwrap(), Some(token), id);
+            target.state = TargetServerState::Initial;
+        }
+        Err(e) => abort_connection(
+            &mut client,
+            &mut target,
+            &mut connection_error,
+            format!("Could not get a connect token ({})", e),
+        ),
+    }
 }
 
-pub fn init_server_connection(
-    mut commands: Commands,
-    target: Res<TargetServer>,
-    current_player_id: Res<CurrentPlayerProfile>,
+/// Replaces the network resources by new ones, connected to this server
+fn connect_to_server(
+    commands: &mut Commands,
+    addr: SocketAddr,
+    connect_token: Option<ConnectToken>,
+    id: PlayerId,
 ) {
-    let addr = target.address.unwrap();
-    let id = current_player_id.into_inner().id;
     commands.add(move |world: &mut World| {
         world.remove_resource::<RenetClient>();
         world.remove_resource::<NetcodeClientTransport>();
@@ -192,11 +219,14 @@ pub fn init_server_connection(
 
         info!("Attempting to connect to: {}", addr);
 
-        let authentication = ClientAuthentication::Unsecure {
-            server_addr: addr,
-            client_id: id,
-            user_data: None,
-            protocol_id: shared::PROTOCOL_ID,
+        let authentication = match connect_token {
+            Some(connect_token) => ClientAuthentication::Secure { connect_token },
+            None => ClientAuthentication::Unsecure {
+                server_addr: addr,
+                client_id: id,
+                user_data: None,
+                protocol_id: shared::PROTOCOL_ID,
+            },
         };
         let socket = UdpSocket::bind("0.0.0.0:0").unwrap();
         let current_time = SystemTime::now()
@@ -207,9 +237,12 @@ pub fn init_server_connection(
         world.insert_resource(transport);
 
         world.insert_resource(CachedChatConversation { ..default() });
+        world.insert_resource(LastWorldUpdate::default());
+        world.insert_resource(ServerClock::default());
+        world.insert_resource(PendingInputs::default());
 
         info!("Network subsystem initialized");
-    })
+    });
 }
 
 pub fn network_failure_handler(mut renet_error: EventReader<NetcodeTransportError>) {
@@ -218,11 +251,34 @@ pub fn network_failure_handler(mut renet_error: EventReader<NetcodeTransportErro
     }
 }
 
+/// Aborts the connection, the loading screen displays the reason before going back to the menu
+fn abort_connection(
+    client: &mut RenetClient,
+    target: &mut TargetServer,
+    connection_error: &mut ConnectionError,
+    reason: String,
+) {
+    error!("Connection refused: {}", reason);
+    client.disconnect();
+    target.address = None;
+    target.username = None;
+    target.session_token = None;
+    target.state = TargetServerState::Refused;
+    connection_error.reason = Some(reason);
+}
+
 pub fn establish_authenticated_connection_to_server(
     mut client: ResMut<RenetClient>,
     mut target: ResMut<TargetServer>,
     current_profile: Res<CurrentPlayerProfile>,
     mut ev_spawn: EventWriter<PlayerSpawnEvent>,
+    mut connection_error: ResMut<ConnectionError>,
+    mut inventory: ResMut<Inventory>,
+    (mut ev_handshake, mut ev_auth, mut ev_unreadable): (
+        EventReader<HandshakeResponseEvent>,
+        EventReader<AuthRegisterResponseEvent>,
+        EventReader<UnreadableMessageEvent>,
+    ),
 ) {
     if target.session_token.is_some() {
         info!(
@@ -232,30 +288,106 @@ pub fn establish_authenticated_connection_to_server(
         return;
     }
 
+    // The connection starts once the connect token is received
+    if target.state == TargetServerState::Refused
+        || target.state == TargetServerState::RequestingToken
+    {
+        return;
+    }
+
+    // The server may have closed the connection, or never answered
+    if client.is_disconnected() {
+        abort_connection(
+            &mut client,
+            &mut target,
+            &mut connection_error,
+            "Disconnected from the server".into(),
+        );
+        return;
+    }
+
+    // The versions are checked before anything else is sent
     if target.state == TargetServerState::Initial {
-        if target.username.is_none() {
-            target.username = Some(current_profile.into_inner().name.clone());
-        }
+        let handshake_msg = ClientToServerMessage::Handshake(HandshakeRequest::current());
+        let handshake_msg_encoded = bincode::options().serialize(&handshake_msg).unwrap();
+        client.send_message(DefaultChannel::ReliableOrdered, handshake_msg_encoded);
+        target.state = TargetServerState::Handshaking;
+    }
 
-        let username = target.username.as_ref().unwrap();
+    for HandshakeResponseEvent(response) in ev_handshake.read() {
+        match response {
+            HandshakeResponse::Accepted {
+                game_version,
+                features,
+            } => {
+                info!(
+                    "Handshake accepted by server {} (features: {:#x})",
+                    game_version, features
+                );
 
-        let auth_msg = ClientToServerMessage::AuthRegisterRequest(AuthRegisterRequest {
-            username: username.clone(),
-        });
-        let auth_msg_encoded = bincode::options().serialize(&auth_msg).unwrap();
-        client.send_message(DefaultChannel::ReliableOrdered, auth_msg_encoded);
-        target.state = TargetServerState::Establising;
+                if target.username.is_none() {
+                    target.username = Some(current_profile.name.clone());
+                }
+
+                let username = target.username.as_ref().unwrap();
+
+                let auth_msg = ClientToServerMessage::AuthRegisterRequest(AuthRegisterRequest {
+                    username: username.clone(),
+                });
+                let auth_msg_encoded = bincode::options().serialize(&auth_msg).unwrap();
+                client.send_message(DefaultChannel::ReliableOrdered, auth_msg_encoded);
+                target.state = TargetServerState::Establising;
+            }
+            HandshakeResponse::Rejected { reason } => {
+                abort_connection(
+                    &mut client,
+                    &mut target,
+                    &mut connection_error,
+                    reason.clone(),
+                );
+                return;
+            }
+        }
     }
 
-    while let Some(message) = client.receive_message(DefaultChannel::ReliableOrdered) {
-        let message =
-            bincode::options().deserialize::<shared::messages::AuthRegisterResponse>(&message);
-        if let Ok(message) = message {
-            target.username = Some(message.username);
-            target.session_token = Some(message.session_token);
-            target.state = TargetServerState::ConnectionEstablished;
-            ev_spawn.send(message.spawn_event);
-            info!("Connected! {:?}", target);
+    // A server built from another version answers with something unreadable
+    if let Some(event) = ev_unreadable.read().last() {
+        if target.state == TargetServerState::Handshaking {
+            abort_connection(
+                &mut client,
+                &mut target,
+                &mut connection_error,
+                format!("Incompatible server version ({})", event.error),
+            );
+            return;
+        }
+    }
+
+    for AuthRegisterResponseEvent(response) in ev_auth.read() {
+        match response {
+            AuthRegisterResponse::Accepted {
+                username,
+                session_token,
+                spawn_event,
+                inventory: saved_inventory,
+            } => {
+                // The server keeps the inventory of the player between sessions
+                *inventory = saved_inventory.clone();
+                target.username = Some(username.clone());
+                target.session_token = Some(*session_token);
+                target.state = TargetServerState::ConnectionEstablished;
+                ev_spawn.send(spawn_event.clone());
+                info!("Connected! {:?}", target);
+            }
+            AuthRegisterResponse::Rejected(reason) => {
+                abort_connection(
+                    &mut client,
+                    &mut target,
+                    &mut connection_error,
+                    reason.to_string(),
+                );
+                return;
+            }
         }
     }
 }
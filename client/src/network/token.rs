@@ -0,0 +1,27 @@
+use bevy_renet::renet::transport::ConnectToken;
+use shared::TOKEN_SERVICE_PORT_OFFSET;
+use std::io::{Read, Write};
+use std::net::{SocketAddr, TcpStream};
+use std::time::Duration;
+
+/// Asks the token service running next to the server for a connect token\
+/// Blocks until the service answers, it must not be called from a system
+pub fn request_connect_token(
+    server_addr: SocketAddr,
+    username: &str,
+) -> Result<ConnectToken, Box<dyn std::error::Error>> {
+    let service_port = server_addr
+        .port()
+        .checked_add(TOKEN_SERVICE_PORT_OFFSET)
+        .ok_or_else(|| format!("No token service port after port {}", server_addr.port()))?;
+    let service_addr = SocketAddr::new(server_addr.ip(), service_port);
+    let mut stream = TcpStream::connect_timeout(&service_addr, Duration::from_secs(1))?;
+    stream.set_read_timeout(Some(Duration::from_secs(3)))?;
+
+    stream.write_all(format!("{}\n", username).as_bytes())?;
+
+    let mut payload: Vec<u8> = Vec::new();
+    stream.read_to_end(&mut payload)?;
+    let token = ConnectToken::read(&mut payload.as_slice())?;
+    Ok(token)
+}
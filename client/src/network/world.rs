@@ -1,13 +1,12 @@
 use crate::{
-    player::{CurrentPlayerMarker, Player},
+    player::{CurrentPlayerMarker, Player, PlayerSnapshot, RemotePlayerSnapshots, ServerClock},
     world::ClientChunk,
 };
 use bevy::prelude::*;
-use bevy_renet::renet::{DefaultChannel, RenetClient};
-use bincode::Options;
+use bevy_renet::renet::RenetClient;
 use shared::{
-    messages::{PlayerSpawnEvent, ServerToClientMessage},
-    world::{block_to_chunk_coord, chunk_in_radius},
+    messages::PlayerMovementState,
+    world::{block_to_chunk_coord, chunk_in_radius, global_block_to_chunk_pos},
 };
 
 use crate::world::ClientWorldMap;
@@ -17,18 +16,29 @@ use crate::world::RenderDistance;
 use crate::world::WorldRenderRequestUpdateEvent;
 
 use super::api::send_network_action;
+use super::{BlockChangesEvent, WorldUpdateEvent};
+
+/// State of the most recent `WorldUpdate` applied, reset for each connection
+#[derive(Resource, Default)]
+pub struct LastWorldUpdate {
+    pub tick: u64,
+    /// Authoritative position and movement state of the current player, not reconciled yet
+    pub current_player_state: Option<(Vec3, PlayerMovementState)>,
+}
 
 pub fn update_world_from_network(
-    client: &mut ResMut<RenetClient>,
-    world: &mut ResMut<ClientWorldMap>,
+    mut world: ResMut<ClientWorldMap>,
     mut client_time: ResMut<ClientTime>,
-    ev_render: &mut EventWriter<WorldRenderRequestUpdateEvent>,
-    players: &mut Query<(&mut Transform, &Player), With<Player>>,
+    mut ev_render: EventWriter<WorldRenderRequestUpdateEvent>,
+    mut players: Query<(&Transform, &Player, Option<&mut RemotePlayerSnapshots>)>,
     current_player_entity: Query<Entity, With<CurrentPlayerMarker>>,
     render_distance: Res<RenderDistance>,
-    ev_spawn: &mut EventWriter<PlayerSpawnEvent>,
+    mut last_update: ResMut<LastWorldUpdate>,
+    (time, mut server_clock): (Res<Time>, ResMut<ServerClock>),
+    mut ev_world_update: EventReader<WorldUpdateEvent>,
+    mut ev_block_changes: EventReader<BlockChangesEvent>,
 ) {
-    let (player_pos, current_player) = players.get(current_player_entity.single()).unwrap();
+    let (player_pos, current_player, _) = players.get(current_player_entity.single()).unwrap();
     let current_player_id = current_player.id;
 
     let player_pos = IVec3::new(
@@ -38,64 +48,103 @@ pub fn update_world_from_network(
     );
     let r = render_distance.distance as i32;
 
-    while let Some(bytes) = client.receive_message(DefaultChannel::ReliableUnordered) {
-        let msg = bincode::options()
-            .deserialize::<ServerToClientMessage>(&bytes)
-            .unwrap();
+    // Full chunks are applied before the block changes which may modify them
+    for WorldUpdateEvent(world_update) in ev_world_update.read() {
+        debug!(
+            "Received world update, {} chunks received",
+            world_update.new_map.len()
+        );
 
-        match msg {
-            ServerToClientMessage::WorldUpdate(world_update) => {
-                debug!(
-                    "Received world update, {} chunks received",
-                    world_update.new_map.len()
-                );
+        trace!("Chunks positions : {:?}", world_update.new_map.keys());
 
-                trace!("Chunks positions : {:?}", world_update.new_map.keys());
+        for (pos, chunk) in world_update.new_map.iter() {
+            // If the chunk is not in render distance range or is empty, do not consider it
+            if !chunk_in_radius(&player_pos, pos, r) || chunk.blocks.is_empty() {
+                continue;
+            }
 
-                for (pos, chunk) in world_update.new_map {
-                    // If the chunk is not in render distance range or is empty, do not consider it
-                    if !chunk_in_radius(&player_pos, &pos, r) || chunk.map.is_empty() {
-                        continue;
+            let chunk = ClientChunk {
+                blocks: chunk.blocks.clone(),
+                entity: {
+                    if let Some(c) = world.map.get(pos) {
+                        c.entity
+                    } else {
+                        None
                     }
+                },
+            };
 
-                    let chunk = ClientChunk {
-                        map: chunk.map,
-                        entity: {
-                            if let Some(c) = world.map.get(&pos) {
-                                c.entity
-                            } else {
-                                None
-                            }
-                        },
-                    };
-
-                    world.map.insert(pos, chunk);
-                    ev_render.send(WorldRenderRequestUpdateEvent::ChunkToReload(pos));
-                }
+            world.map.insert(*pos, chunk);
+            ev_render.send(WorldRenderRequestUpdateEvent::ChunkToReload(*pos));
+        }
+
+        // Updates can arrive out of order, older positions must not be applied
+        if world_update.tick < last_update.tick {
+            continue;
+        }
+        last_update.tick = world_update.tick;
 
-                debug!("Player pos {:?}", world_update.player_positions);
+        debug!("Player pos {:?}", world_update.player_positions);
+
+        // The position of the current player is predicted, it is reconciled with the inputs not acknowledged yet
+        if let (Some(position), Some(state)) = (
+            world_update.player_positions.get(&current_player_id),
+            world_update.player_states.get(&current_player_id),
+        ) {
+            last_update.current_player_state = Some((*position, *state));
+        }
 
-                for (mut transform, player) in players.iter_mut() {
-                    debug!("Player found: {} at {:?}", player.name, transform);
-                    if player.id == current_player_id {
+        // The other players are moved smoothly between the received positions
+        server_clock.update(world_update.timestamp, time.elapsed_seconds_f64());
+        for (transform, player, snapshots) in players.iter_mut() {
+            trace!("Player found: {} at {:?}", player.name, transform);
+            let Some(mut snapshots) = snapshots else {
+                continue;
+            };
+            if let Some(position) = world_update.player_positions.get(&player.id) {
+                snapshots.push(PlayerSnapshot {
+                    timestamp: world_update.timestamp,
+                    position: *position,
+                    orientation: world_update
+                        .player_orientations
+                        .get(&player.id)
+                        .copied()
+                        .unwrap_or_default(),
+                });
+                trace!("New snapshot {} => {:?}", player.id, position);
+            }
+        }
+
+        // get current time
+        client_time.0 = world_update.time;
+    }
+
+    for BlockChangesEvent(block_changes) in ev_block_changes.read() {
+        trace!(
+            "Received {} block changes at tick {}",
+            block_changes.changes.len(),
+            block_changes.tick
+        );
+
+        for change in block_changes.changes.iter() {
+            let chunk_pos = global_block_to_chunk_pos(&change.position);
+            match change.block {
+                Some(block) => {
+                    // Empty chunks are not sent by the server, a block can be placed in one of them
+                    if !world.map.contains_key(&chunk_pos)
+                        && !chunk_in_radius(&player_pos, &chunk_pos, r)
+                    {
                         continue;
                     }
-                    let vec3 = world_update.player_positions.get(&player.id);
-                    if let Some(vec3) = vec3 {
-                        let new_transform = Transform::from_translation(*vec3);
-                        *transform = new_transform;
-                        debug!("Set transform {} => {:?}", player.id, new_transform);
-                    }
+                    world.set_block(&change.position, block);
+                }
+                None => {
+                    world.remove_block_by_coordinates(&change.position);
                 }
-
-                // get current time
-                client_time.0 = world_update.time;
-            }
-            ServerToClientMessage::PlayerSpawn(spawn_event) => {
-                info!("Received SINGLE spawn event {:?}", spawn_event);
-                ev_spawn.send(spawn_event);
             }
-            _ => {}
+            ev_render.send(WorldRenderRequestUpdateEvent::BlockToReload(
+                change.position,
+            ));
         }
     }
 }
@@ -1,9 +1,7 @@
-use crate::camera::CameraController;
-use crate::constants::GRAVITY;
 use crate::input::data::GameAction;
 use crate::input::keyboard::*;
 use crate::network::request_world_update;
-use crate::player::{Player, ViewMode};
+use crate::player::ViewMode;
 use crate::ui::hud::debug::DebugOptions;
 use crate::ui::hud::UIMode;
 use crate::world::render_distance::RenderDistance;
@@ -14,75 +12,11 @@ use bevy_renet::renet::RenetClient;
 use shared::world::{block_to_chunk_coord, chunk_in_radius};
 
 use super::CurrentPlayerMarker;
-use crate::world::FirstChunkReceived;
 
-fn is_block_at_position(position: Vec3, world_map: &ClientWorldMap) -> bool {
-    if let Some(block) = world_map.get_block_by_coordinates(&IVec3::new(
-        position.x.floor() as i32,
-        position.y.floor() as i32,
-        position.z.floor() as i32,
-    )) {
-        block.id.has_hitbox()
-    } else {
-        false
-    }
-}
-
-fn check_player_collision(
-    player_position: Vec3,
-    player: &Player,
-    world_map: &ClientWorldMap,
-) -> bool {
-    // Vérification de la collision avec les pieds et la tête du joueur
-    let foot_position = Vec3::new(
-        player_position.x,
-        player_position.y - player.height / 2.0,
-        player_position.z,
-    );
-    let head_position = Vec3::new(
-        player_position.x,
-        player_position.y + player.height / 2.0,
-        player_position.z,
-    );
-
-    // On vérifie les coins du joueur
-    let offsets = [
-        Vec3::new(-player.width / 2.0, 0.0, -player.width / 2.0), // bas gauche devant
-        Vec3::new(player.width / 2.0, 0.0, -player.width / 2.0),  // bas droite devant
-        Vec3::new(-player.width / 2.0, 0.0, player.width / 2.0),  // bas gauche derrière
-        Vec3::new(player.width / 2.0, 0.0, player.width / 2.0),   // bas droite derrière
-    ];
-
-    // Vérifier la collision au niveau des pieds
-    for offset in &offsets {
-        let check_pos = foot_position + *offset;
-        if is_block_at_position(check_pos, world_map) {
-            return true;
-        }
-    }
-
-    // Vérifier la collision au niveau de la tête
-    for offset in &offsets {
-        let check_pos = head_position + *offset;
-        if is_block_at_position(check_pos, world_map) {
-            return true;
-        }
-    }
-
-    false
-}
-
-// System to move the player based on keyboard input
+// System handling the player controls, the movements themselves are computed by the server
 pub fn player_movement_system(
-    queries: (
-        Query<
-            (&mut Transform, &mut Player, &mut Handle<StandardMaterial>),
-            With<CurrentPlayerMarker>,
-        >,
-        Query<&Transform, (With<Camera>, With<CameraController>, Without<Player>)>,
-    ),
+    player_query: Query<(&Transform, &Handle<StandardMaterial>), With<CurrentPlayerMarker>>,
     resources: (
-        Res<Time>,
         Res<ButtonInput<KeyCode>>,
         Res<UIMode>,
         Res<KeyMap>,
@@ -96,11 +30,8 @@ pub fn player_movement_system(
     mut previous_player_chunk: Local<IVec3>,
     mut commands: Commands,
     mut ev_writer: EventWriter<WorldRenderRequestUpdateEvent>,
-    first_chunk_received: ResMut<FirstChunkReceived>,
 ) {
-    let (mut player_query, camera_query) = queries;
     let (
-        time,
         keyboard_input,
         ui_mode,
         key_map,
@@ -112,8 +43,7 @@ pub fn player_movement_system(
         mut client,
     ) = resources;
 
-    let (mut player_transform, mut player, material_handle_mut_ref) = player_query.single_mut();
-    let camera_transform = camera_query.single();
+    let (player_transform, material_handle) = player_query.single();
 
     if *ui_mode == UIMode::Closed {
         if is_action_just_pressed(GameAction::ToggleViewMode, &keyboard_input, &key_map) {
@@ -123,11 +53,6 @@ pub fn player_movement_system(
         if is_action_just_pressed(GameAction::ToggleChunkDebugMode, &keyboard_input, &key_map) {
             debug_options.toggle_chunk_debug_mode();
         }
-
-        // fly mode (f key)
-        if is_action_just_pressed(GameAction::ToggleFlyMode, &keyboard_input, &key_map) {
-            player.toggle_fly_mode();
-        }
     }
 
     let force_chunk_reload =
@@ -161,7 +86,7 @@ pub fn player_movement_system(
         // Only retain chunks in the render radius
         world_map.map.retain(|pos, chunk| {
             // If chunk is empty, or not in render radius
-            if !chunk_in_radius(&player_chunk, pos, r) || chunk.map.is_empty() {
+            if !chunk_in_radius(&player_chunk, pos, r) || chunk.blocks.is_empty() {
                 // Remove chunk, and delete its associated entity if it exists
                 if let Some(entity) = chunk.entity {
                     commands.entity(entity).despawn_recursive();
@@ -199,7 +124,6 @@ pub fn player_movement_system(
         }
     }
 
-    let material_handle = &*material_handle_mut_ref;
     match *view_mode {
         ViewMode::FirstPerson => {
             // make player transparent
@@ -213,108 +137,4 @@ pub fn player_movement_system(
             }
         }
     }
-
-    let speed = if player.is_flying { 15.0 } else { 5.0 };
-
-    let jump_velocity = 10.0;
-
-    // flying mode
-    if player.is_flying && *ui_mode == UIMode::Closed {
-        if is_action_pressed(GameAction::FlyUp, &keyboard_input, &key_map) {
-            player_transform.translation.y += speed * 2.0 * time.delta_seconds();
-        }
-        if is_action_pressed(GameAction::FlyDown, &keyboard_input, &key_map) {
-            player_transform.translation.y -= speed * 2.0 * time.delta_seconds();
-        }
-    }
-
-    // Calculate movement directions relative to the camera
-    let mut forward = camera_transform.forward().xyz();
-    forward.y = 0.0;
-
-    let mut right = camera_transform.right().xyz();
-    right.y = 0.0;
-
-    let mut direction = Vec3::ZERO;
-
-    if *ui_mode == UIMode::Closed {
-        // Adjust direction based on key presses
-        if is_action_pressed(GameAction::MoveBackward, &keyboard_input, &key_map) {
-            direction -= forward;
-        }
-        if is_action_pressed(GameAction::MoveForward, &keyboard_input, &key_map) {
-            direction += forward;
-        }
-        if is_action_pressed(GameAction::MoveLeft, &keyboard_input, &key_map) {
-            direction -= right;
-        }
-        if is_action_pressed(GameAction::MoveRight, &keyboard_input, &key_map) {
-            direction += right;
-        }
-    }
-
-    // Move the player (xy plane only), only if there are no blocks and UI is closed
-    if first_chunk_received.0 && direction.length_squared() > 0.0 {
-        direction = direction.normalize();
-
-        // Déplacement sur l'axe X
-        let new_pos_x = player_transform.translation
-            + Vec3::new(direction.x, 0.0, 0.0) * speed * time.delta_seconds();
-
-        if player.is_flying || !check_player_collision(new_pos_x, &player, &world_map) {
-            player_transform.translation.x = new_pos_x.x;
-        }
-
-        // Déplacement sur l'axe Z
-        let new_pos_z = player_transform.translation
-            + Vec3::new(0.0, 0.0, direction.z) * speed * time.delta_seconds();
-
-        if player.is_flying || !check_player_collision(new_pos_z, &player, &world_map) {
-            player_transform.translation.z = new_pos_z.z;
-        }
-    }
-
-    // Handle jumping (if on the ground) and gravity, only if not flying
-    if !player.is_flying {
-        if player.on_ground && is_action_pressed(GameAction::Jump, &keyboard_input, &key_map) {
-            // Player can jump only when grounded
-            player.vertical_velocity = jump_velocity;
-            player.on_ground = false;
-        } else if !player.on_ground {
-            // Apply gravity when the player is in the air
-            player.vertical_velocity += GRAVITY * time.delta_seconds();
-        }
-    }
-
-    // apply gravity and verify vertical collisions
-    let mut new_y = player_transform.translation.y;
-    if first_chunk_received.0 {
-        new_y = player_transform.translation.y + player.vertical_velocity * time.delta_seconds();
-    }
-
-    // Vérifier uniquement les collisions verticales (sol et plafond)
-    if check_player_collision(
-        Vec3::new(
-            player_transform.translation.x,
-            new_y,
-            player_transform.translation.z,
-        ),
-        &player,
-        &world_map,
-    ) {
-        // Si un bloc est détecté sous le joueur, il reste sur le bloc
-        player.on_ground = true;
-        player.vertical_velocity = 0.0; // Réinitialiser la vélocité verticale si le joueur est au sol
-    } else {
-        // Si aucun bloc n'est détecté sous le joueur, il continue de tomber
-        player_transform.translation.y = new_y;
-        player.on_ground = false;
-    }
-
-    // If the player is below the world, reset their position
-    const FALL_LIMIT: f32 = -50.0;
-    if player_transform.translation.y < FALL_LIMIT {
-        player_transform.translation = Vec3::new(0.0, 100.0, 0.0);
-        player.vertical_velocity = 0.0;
-    }
 }
@@ -1,5 +1,5 @@
 use crate::camera::*;
-use crate::constants::{CUBE_SIZE, INTERACTION_DISTANCE};
+use crate::constants::CUBE_SIZE;
 use crate::network::api::send_network_action;
 use crate::network::api::NetworkAction;
 use crate::player::inventory::*;
@@ -12,7 +12,8 @@ use bevy::math::NormedVectorSpace;
 use bevy::prelude::*;
 use bevy_mod_raycast::prelude::*;
 use bevy_renet::renet::RenetClient;
-use shared::world::{BlockData, ItemStack, ItemType};
+use shared::players::INTERACTION_DISTANCE;
+use shared::world::{BlockData, ItemType};
 
 use super::CurrentPlayerMarker;
 
@@ -33,13 +34,13 @@ pub fn handle_block_interactions(
         ResMut<ClientWorldMap>,
         Res<ButtonInput<MouseButton>>,
         Res<UIMode>,
-        ResMut<Inventory>,
+        Res<Inventory>,
         ResMut<RenetClient>,
     ),
     mut ev_render: EventWriter<WorldRenderRequestUpdateEvent>,
 ) {
     let (player_query, mut p_transform, raycast_source, hotbar) = queries;
-    let (mut world_map, mouse_input, ui_mode, mut inventory, mut client) = resources;
+    let (mut world_map, mouse_input, ui_mode, inventory, mut client) = resources;
 
     let player = player_query.single().clone();
 
@@ -67,18 +68,8 @@ pub fn handle_block_interactions(
                 // Remove the hit block
                 let block = world_map.remove_block_by_coordinates(&global_block_coords);
 
-                if let Some(block) = block {
-                    // add the block to the player's inventory
-
-                    // If block has corresponding item, add it to inventory
-                    for (item_id, nb) in block.id.get_drops(1) {
-                        inventory.add_item_to_inventory(ItemStack {
-                            item_id,
-                            item_type: item_id.get_default_type(),
-                            nb,
-                        });
-                    }
-
+                // The server adds the drops of the block to the inventory
+                if block.is_some() {
                     ev_render.send(WorldRenderRequestUpdateEvent::BlockToReload(
                         global_block_coords,
                     ));
@@ -89,6 +80,7 @@ pub fn handle_block_interactions(
                         NetworkAction::BlockInteraction {
                             position: global_block_coords,
                             block_type: None, // None signify suppression
+                            slot: hotbar.single().selected,
                         },
                     );
                 }
@@ -124,9 +116,9 @@ pub fn handle_block_interactions(
                 && (distance.x.abs() > (CUBE_SIZE + player.width) / 2. || distance.z.abs() > (CUBE_SIZE + player.width ) / 2. || distance.y.abs() > (CUBE_SIZE + player.height) / 2.)
             {
                 // Try to get item currently selected in player hotbar
-                if let Some(&item) = inventory.inner.get(&hotbar.single().selected) {
-                    inventory.remove_item_from_stack(hotbar.single().selected, 1);
-
+                // The server removes it from the inventory, if the player really holds it
+                let slot = hotbar.single().selected;
+                if let Some(&item) = inventory.inner.get(&slot) {
                     // Check if the item has a block counterpart
                     if let ItemType::Block(block_id) = item.item_type {
                         let block_pos =
@@ -144,6 +136,7 @@ pub fn handle_block_interactions(
                             NetworkAction::BlockInteraction {
                                 position: block_pos,
                                 block_type: Some(block), // Some signify adding
+                                slot,
                             },
                         );
                     }
@@ -0,0 +1,162 @@
+use bevy::prelude::*;
+use shared::messages::PlayerOrientation;
+use std::collections::VecDeque;
+use std::f32::consts::{PI, TAU};
+
+/// Delay between the server and the displayed movements of the other players, in seconds\
+/// Leaves time for the next snapshot to arrive, so positions are interpolated between two snapshots
+const INTERPOLATION_DELAY: f64 = 0.1;
+/// Longest time the movement of a player is extrapolated when snapshots are missing, in seconds
+const MAX_EXTRAPOLATION: f64 = 0.25;
+const MAX_SNAPSHOTS: usize = 64;
+/// Players are only sent while they are in the render distance of the client\
+/// Without snapshot for this long, a player left it : it is hidden until it comes back
+const OUT_OF_VIEW_DELAY: f64 = 1.0;
+/// Speed at which the estimated clock of the server follows the received timestamps
+const CLOCK_SMOOTHING: f64 = 0.05;
+
+/// Estimated offset between the clock of the server and the local one, reset for each connection
+#[derive(Resource, Default, Debug)]
+pub struct ServerClock {
+    offset: Option<f64>,
+}
+
+impl ServerClock {
+    pub fn update(&mut self, server_timestamp: f64, local_time: f64) {
+        let sample = server_timestamp - local_time;
+        // Each packet is delayed differently, the estimation follows them slowly to absorb the jitter
+        self.offset = Some(match self.offset {
+            Some(offset) => offset + (sample - offset) * CLOCK_SMOOTHING,
+            None => sample,
+        });
+    }
+
+    /// Server time at which the other players are displayed
+    fn render_time(&self, local_time: f64) -> Option<f64> {
+        self.offset
+            .map(|offset| local_time + offset - INTERPOLATION_DELAY)
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PlayerSnapshot {
+    /// Server time, in seconds
+    pub timestamp: f64,
+    pub position: Vec3,
+    pub orientation: PlayerOrientation,
+}
+
+/// Recent positions of another player received from the server, ordered by timestamp
+#[derive(Component, Default, Debug)]
+pub struct RemotePlayerSnapshots {
+    snapshots: VecDeque<PlayerSnapshot>,
+    /// Velocity between the two last snapshots, used to extrapolate
+    velocity: Vec3,
+}
+
+/// Pivot of the head of the other players, turned by their pitch
+#[derive(Component)]
+pub struct PlayerHead;
+
+impl RemotePlayerSnapshots {
+    pub fn push(&mut self, snapshot: PlayerSnapshot) {
+        // Updates can arrive out of order
+        if let Some(last) = self.snapshots.back() {
+            if snapshot.timestamp <= last.timestamp {
+                return;
+            }
+            self.velocity =
+                (snapshot.position - last.position) / (snapshot.timestamp - last.timestamp) as f32;
+        }
+
+        self.snapshots.push_back(snapshot);
+        if self.snapshots.len() > MAX_SNAPSHOTS {
+            self.snapshots.pop_front();
+        }
+    }
+
+    /// Whether no snapshot was received for a while before the given server time
+    fn is_out_of_view(&self, render_time: f64) -> bool {
+        self.snapshots
+            .back()
+            .is_none_or(|last| render_time - last.timestamp > OUT_OF_VIEW_DELAY)
+    }
+
+    /// Computes the position of the player at the given server time
+    fn sample(&mut self, render_time: f64) -> Option<(Vec3, PlayerOrientation)> {
+        // Only the last snapshot before the render time is still needed
+        while self.snapshots.len() >= 2 && self.snapshots[1].timestamp <= render_time {
+            self.snapshots.pop_front();
+        }
+
+        let from = *self.snapshots.front()?;
+        if render_time <= from.timestamp {
+            return Some((from.position, from.orientation));
+        }
+
+        match self.snapshots.get(1) {
+            Some(to) => {
+                let t = ((render_time - from.timestamp) / (to.timestamp - from.timestamp)) as f32;
+                Some((
+                    from.position.lerp(to.position, t),
+                    PlayerOrientation {
+                        yaw: lerp_angle(from.orientation.yaw, to.orientation.yaw, t),
+                        pitch: from.orientation.pitch
+                            + (to.orientation.pitch - from.orientation.pitch) * t,
+                    },
+                ))
+            }
+            None => {
+                // No newer snapshot yet, the player keeps moving for a short time
+                let elapsed = (render_time - from.timestamp).min(MAX_EXTRAPOLATION) as f32;
+                Some((from.position + self.velocity * elapsed, from.orientation))
+            }
+        }
+    }
+}
+
+/// Interpolates between two angles, going through the shortest way
+fn lerp_angle(from: f32, to: f32, t: f32) -> f32 {
+    let diff = (to - from + PI).rem_euclid(TAU) - PI;
+    from + diff * t
+}
+
+/// Moves the other players between the snapshots received from the server\
+/// Players out of the view of the client are hidden
+pub fn interpolate_remote_players(
+    time: Res<Time>,
+    clock: Res<ServerClock>,
+    mut players: Query<(
+        &mut Transform,
+        &mut RemotePlayerSnapshots,
+        &mut Visibility,
+        &Children,
+    )>,
+    mut heads: Query<&mut Transform, (With<PlayerHead>, Without<RemotePlayerSnapshots>)>,
+) {
+    let Some(render_time) = clock.render_time(time.elapsed_seconds_f64()) else {
+        return;
+    };
+
+    for (mut transform, mut snapshots, mut visibility, children) in players.iter_mut() {
+        let expected_visibility = if snapshots.is_out_of_view(render_time) {
+            Visibility::Hidden
+        } else {
+            Visibility::Inherited
+        };
+        visibility.set_if_neq(expected_visibility);
+
+        let Some((position, orientation)) = snapshots.sample(render_time) else {
+            continue;
+        };
+
+        transform.translation = position;
+        transform.rotation = orientation.body_rotation();
+
+        for child in children.iter() {
+            if let Ok(mut head_transform) = heads.get_mut(*child) {
+                head_transform.rotation = orientation.head_rotation();
+            }
+        }
+    }
+}
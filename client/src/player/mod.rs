@@ -1,8 +1,10 @@
 mod controller;
 mod interactions;
+mod interpolation;
 pub(crate) mod inventory;
 mod spawn;
 
 pub use controller::*;
 pub use interactions::*;
+pub use interpolation::*;
 pub use spawn::*;
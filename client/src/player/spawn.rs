@@ -3,21 +3,10 @@ use crate::{
     GameState,
 };
 use bevy::prelude::*;
-use shared::messages::{PlayerId, PlayerSpawnEvent};
+use shared::messages::{PlayerDespawnEvent, PlayerSpawnEvent};
 
-#[derive(Component, Clone)]
-pub struct Player {
-    pub id: PlayerId,
-    pub name: String,
-    pub vertical_velocity: f32,
-    pub on_ground: bool,
-    // pub view_mode: ViewMode,
-    // pub is_chunk_debug_mode_enabled: bool,
-    pub is_flying: bool,
-    // pub inventory: HashMap<RegistryId, items::Item>,
-    pub height: f32,
-    pub width: f32,
-}
+use super::{PlayerHead, RemotePlayerSnapshots};
+pub use shared::players::Player;
 
 #[derive(Component)]
 pub struct CurrentPlayerMarker {}
@@ -37,25 +26,6 @@ impl ViewMode {
     }
 }
 
-impl Player {
-    pub fn new(id: PlayerId, name: String) -> Self {
-        Self {
-            id,
-            name,
-            vertical_velocity: 0.0,
-            on_ground: true,
-            is_flying: false,
-            height: 1.8,
-            width: 0.8,
-        }
-    }
-
-    pub fn toggle_fly_mode(&mut self) {
-        self.is_flying = !self.is_flying;
-        self.vertical_velocity = 0.0; // Réinitialisation de la vélocité
-    }
-}
-
 pub fn spawn_player(
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
@@ -66,7 +36,6 @@ pub fn spawn_player(
     players: Query<&Player>,
 ) {
     let current_id = player_profile.into_inner().id;
-    let spawn_coords = Vec3::new(7.5, 80.0, 7.5);
     'event_loop: for event in ev_spawn.read() {
         info!("Executing spawn player for event: {:?}", event);
         for player in players.iter() {
@@ -79,7 +48,10 @@ pub fn spawn_player(
             }
         }
         let is_current_player = event.id == current_id;
-        let player = Player::new(event.id, event.name.clone());
+        let mut player = Player::new(event.id, event.name.clone());
+        player.game_mode = event.game_mode;
+        player.orientation = event.orientation;
+        player.spawn_point = event.spawn_point;
 
         let color = if is_current_player {
             Color::srgba(1.0, 0.0, 0.0, 1.0)
@@ -88,6 +60,7 @@ pub fn spawn_player(
         };
 
         info!("Spawning new player object: {}", player.id);
+        let (player_width, player_height) = (player.width, player.height);
 
         let mut entity = commands.spawn((
             StateScoped(GameState::Game),
@@ -98,7 +71,7 @@ pub fn spawn_player(
                     player.width,
                 ))),
                 material: materials.add(color),
-                transform: Transform::from_translation(spawn_coords),
+                transform: Transform::from_translation(event.position),
                 ..Default::default()
             },
             player,
@@ -108,6 +81,50 @@ pub fn spawn_player(
         if is_current_player {
             target_server.state = TargetServerState::FullyReady;
             entity.insert(CurrentPlayerMarker {});
+        } else {
+            // A visor shows where the other players are looking
+            let visor_mesh = meshes.add(Mesh::from(Cuboid::new(0.5, 0.12, 0.05)));
+            let visor_material = materials.add(Color::srgba(0.1, 0.1, 0.1, 1.0));
+            entity
+                .insert(RemotePlayerSnapshots::default())
+                .with_children(|body| {
+                    body.spawn((
+                        SpatialBundle::from_transform(Transform::from_xyz(
+                            0.0,
+                            player_height / 2.0 - 0.1,
+                            0.0,
+                        )),
+                        PlayerHead,
+                    ))
+                    .with_children(|head| {
+                        head.spawn(PbrBundle {
+                            mesh: visor_mesh,
+                            material: visor_material,
+                            transform: Transform::from_xyz(0.0, 0.0, -player_width / 2.0 - 0.025),
+                            ..Default::default()
+                        });
+                    });
+                });
+        }
+    }
+}
+
+pub fn despawn_player(
+    mut commands: Commands,
+    player_profile: Res<CurrentPlayerProfile>,
+    mut ev_despawn: EventReader<PlayerDespawnEvent>,
+    players: Query<(Entity, &Player)>,
+) {
+    for event in ev_despawn.read() {
+        // The current player is only removed when leaving the game
+        if event.id == player_profile.id {
+            continue;
+        }
+        for (entity, player) in players.iter() {
+            if player.id == event.id {
+                info!("Despawning player object: {}", player.id);
+                commands.entity(entity).despawn_recursive();
+            }
         }
     }
 }
@@ -115,7 +115,7 @@ pub fn setup_chat(
 
 pub fn render_chat(
     resources: (
-        Res<CachedChatConversation>,
+        ResMut<CachedChatConversation>,
         Res<AssetServer>,
         ResMut<RenetClient>,
         Res<ButtonInput<KeyCode>>,
@@ -136,12 +136,11 @@ pub fn render_chat(
             Without<ChatRoot>,
         >,
     ),
-    mut last_render_ts: Local<u64>,
     mut event: EventReader<TextInputSubmitEvent>,
     mut commands: Commands,
     _paths: Res<GameFolderPaths>,
 ) {
-    let (cached_conv, asset_server, mut client, keyboard_input, key_map) = resources;
+    let (mut cached_conv, asset_server, mut client, keyboard_input, key_map) = resources;
     let (mut text_query, mut visibility_query, parent_query, mut animation_query) = queries;
 
     let (entity_check, mut inactive, mut value) = text_query.single_mut();
@@ -191,14 +190,18 @@ pub fn render_chat(
         }
     }
 
+    let cached_conv = &mut *cached_conv;
     if let Some(conv) = &cached_conv.data {
         for message in &conv.messages {
-            // If message too old, don't render
-            if message.date <= *last_render_ts {
+            // If message already rendered, skip it
+            if cached_conv
+                .last_rendered_id
+                .is_some_and(|last_id| message.id <= last_id)
+            {
                 continue;
             }
 
-            *last_render_ts = message.date;
+            cached_conv.last_rendered_id = Some(message.id);
 
             let msg = commands
                 .spawn((
@@ -1,11 +1,9 @@
 use crate::player::CurrentPlayerMarker;
 use crate::world::ClientWorldMap;
-use crate::{
-    camera::BlockRaycastSet,
-    constants::{CUBE_SIZE, INTERACTION_DISTANCE},
-};
+use crate::{camera::BlockRaycastSet, constants::CUBE_SIZE};
 use bevy::{math::NormedVectorSpace, prelude::*};
 use bevy_mod_raycast::prelude::RaycastSource;
+use shared::players::INTERACTION_DISTANCE;
 
 #[derive(Component)]
 pub struct BlockText;
@@ -1,10 +1,11 @@
-use super::{add_item_floating_stack, remove_item_floating_stack};
 use crate::constants::MAX_HOTBAR_SLOTS;
 use crate::input::data::GameAction;
 use crate::input::keyboard::is_action_just_pressed;
+use crate::network::api::{send_network_action, NetworkAction};
+use crate::network::CurrentPlayerProfile;
 use crate::player::inventory::Inventory;
 use crate::ui::hud::hotbar::Hotbar;
-use crate::ui::hud::{FloatingStack, InventoryCell, InventoryRoot};
+use crate::ui::hud::{FloatingStack, InventoryCell, InventoryDialog, InventoryRoot};
 use crate::world::MaterialResource;
 use crate::KeyMap;
 use bevy::color::Color;
@@ -19,7 +20,9 @@ use bevy::prelude::{
 use bevy::sprite::TextureAtlas;
 use bevy::ui::{BorderColor, Interaction};
 use bevy::window::PrimaryWindow;
-use shared::world::{ItemId, ItemStack};
+use bevy_renet::renet::RenetClient;
+use shared::players::InventoryAction;
+use shared::world::ItemStack;
 
 pub fn render_inventory_hotbar(
     (
@@ -30,6 +33,7 @@ pub fn render_inventory_hotbar(
         mut visibility_query,
         window_query,
         mut hotbar_query,
+        dialog_query,
     ): (
         Query<&mut Text>,
         Query<(&mut TextureAtlas, &mut Visibility), Without<InventoryRoot>>,
@@ -38,13 +42,16 @@ pub fn render_inventory_hotbar(
         Query<&mut Visibility, With<InventoryRoot>>,
         Query<&Window, With<PrimaryWindow>>,
         Query<&mut Hotbar>,
+        Query<&Interaction, With<InventoryDialog>>,
     ),
-    (keyboard_input, mouse_input, key_map, mut inventory, materials): (
+    (keyboard_input, mouse_input, key_map, inventory, materials, mut client, profile): (
         Res<ButtonInput<KeyCode>>,
         Res<ButtonInput<MouseButton>>,
         Res<KeyMap>,
-        ResMut<Inventory>,
+        Res<Inventory>,
         Res<MaterialResource>,
+        ResMut<RenetClient>,
+        Res<CurrentPlayerProfile>,
     ),
     mut scroll: EventReader<MouseWheel>,
 ) {
@@ -57,24 +64,14 @@ pub fn render_inventory_hotbar(
     }
 
     if is_action_just_pressed(GameAction::DebugGetBlock, &keyboard_input, &key_map) {
-        debug!("Blocks given to user");
-        inventory.add_item_to_inventory(ItemStack {
-            item_id: ItemId::Glass,
-            item_type: ItemId::Glass.get_default_type(),
-            nb: 64,
-        });
-
-        inventory.add_item_to_inventory(ItemStack {
-            item_id: ItemId::Poppy,
-            item_type: ItemId::Poppy.get_default_type(),
-            nb: 64,
-        });
-
-        inventory.add_item_to_inventory(ItemStack {
-            item_id: ItemId::Dandelion,
-            item_type: ItemId::Dandelion.get_default_type(),
-            nb: 64,
-        });
+        debug!("Blocks requested to the server");
+        // The server owns the inventory, the player must be allowed to use `/give`
+        for item in ["glass", "poppy", "dandelion"] {
+            send_network_action(
+                &mut client,
+                NetworkAction::ChatMessage(format!("/give {} {} 64", profile.name, item)),
+            );
+        }
     }
 
     let (mut style, mut floating_stack, children) = floating_stack_query.single_mut();
@@ -97,8 +94,47 @@ pub fn render_inventory_hotbar(
     // Add scrolling
     hotbar_query.single_mut().selected = stack_scrolling.rem_euclid(MAX_HOTBAR_SLOTS as i32) as u32;
 
+    // The floating items are still in their slot on the server, which may have changed since
+    let source = floating_stack
+        .slot
+        .and_then(|slot| inventory.inner.get(&slot).copied());
+    match source {
+        Some(source) => floating_stack.nb = floating_stack.nb.min(source.nb),
+        None => *floating_stack = FloatingStack::default(),
+    }
+    // Closing the inventory puts them back
+    if *vis != Visibility::Visible {
+        *floating_stack = FloatingStack::default();
+    }
+
+    // Clicking out of the inventory throws them away
+    let hovering_cell = cursor_query
+        .iter()
+        .any(|(interaction, ..)| *interaction != Interaction::None);
+    if mouse_input.just_pressed(MouseButton::Left)
+        && !hovering_cell
+        && *dialog_query.single() == Interaction::None
+    {
+        if let Some(slot) = floating_stack.slot {
+            send_network_action(
+                &mut client,
+                NetworkAction::InventoryAction(InventoryAction::Drop {
+                    slot,
+                    nb: floating_stack.nb,
+                }),
+            );
+            *floating_stack = FloatingStack::default();
+        }
+    }
+
+    let floating_items = source
+        .filter(|_| floating_stack.slot.is_some())
+        .map(|source| ItemStack {
+            nb: floating_stack.nb,
+            ..source
+        });
     update_inventory_cell(
-        &floating_stack.items,
+        &floating_items,
         &mut txt,
         &mut stack_vis,
         &mut stack_atlas,
@@ -120,8 +156,17 @@ pub fn render_inventory_hotbar(
         let mut txt: bevy::prelude::Mut<'_, Text> = text_query.get_mut(children[0]).unwrap();
         let (mut stack_atlas, mut stack_vis) = atlas_query.get_mut(children[1]).unwrap();
 
+        // The floating items are not shown in their cell
+        let displayed_stack = match (stack, floating_stack.slot) {
+            (Some(stack), Some(slot)) if slot == cell.id => Some(ItemStack {
+                nb: stack.nb - floating_stack.nb,
+                ..stack
+            })
+            .filter(|stack| stack.nb > 0),
+            _ => stack,
+        };
         update_inventory_cell(
-            &stack,
+            &displayed_stack,
             &mut txt,
             &mut stack_vis,
             &mut stack_atlas,
@@ -141,99 +186,85 @@ pub fn render_inventory_hotbar(
         }
         // Means we have an interaction with the cell, but which type of interaction ?
 
-        let floating_items = floating_stack.items;
-
-        // Using variables to avoid E0502 errors -_-
-        let stack_exists = stack.is_some();
-        let floating_exists = floating_items.is_some();
-
         // In case LMB pressed :
         if mouse_input.just_pressed(MouseButton::Left) {
-            // Transfer items from inventory cell to floating stack
+            match (floating_stack.slot, source) {
+                // Put the floating stack back in its slot
+                (Some(slot), _) if slot == cell.id => {
+                    *floating_stack = FloatingStack::default();
+                }
+                // Put the floating stack down in this cell
+                (Some(slot), Some(source)) => {
+                    if stack.is_none_or(|stack| stack.item_id == source.item_id) {
+                        let action = if floating_stack.split {
+                            InventoryAction::Split {
+                                from: slot,
+                                to: cell.id,
+                            }
+                        } else {
+                            InventoryAction::Move {
+                                from: slot,
+                                to: cell.id,
+                                nb: floating_stack.nb,
+                            }
+                        };
+                        send_network_action(&mut client, NetworkAction::InventoryAction(action));
 
-            if stack_exists
-                && floating_exists
-                && stack.unwrap().item_id == floating_items.unwrap().item_id
-                && stack.unwrap().nb < stack.unwrap().item_id.get_max_stack()
-            {
-                let stack = stack.unwrap();
-                let floating_items = floating_items.unwrap();
-                inventory.add_item_to_stack(
-                    cell.id,
-                    remove_item_floating_stack(
-                        &mut floating_stack,
-                        stack.item_id.get_max_stack() - stack.nb,
-                    ),
-                    floating_items.item_id,
-                    stack.item_type,
-                );
-            } else {
-                if stack_exists {
-                    let stack = stack.unwrap();
-                    floating_stack.items = Some(stack);
-                    // If no exchange is made with floating stack, clear cell
-                    if !floating_exists {
-                        inventory.inner.remove(&cell.id);
+                        // Items which do not fit in the cell stay in the floating stack
+                        let room = source
+                            .item_id
+                            .get_max_stack()
+                            .saturating_sub(stack.map_or(0, |stack| stack.nb));
+                        put_down_floating_items(&mut floating_stack, room);
+                    } else if floating_stack.nb == source.nb {
+                        send_network_action(
+                            &mut client,
+                            NetworkAction::InventoryAction(InventoryAction::Swap {
+                                first: slot,
+                                second: cell.id,
+                            }),
+                        );
+                        *floating_stack = FloatingStack::default();
                     }
                 }
-
-                // Transfer items from floating stack to inventory cell
-                if floating_exists {
-                    let floating_items = floating_items.unwrap();
-                    inventory.inner.insert(cell.id, floating_items);
-                    // If no exchange is made with cell, clear floating stack
-                    if !stack_exists {
-                        floating_stack.items = None;
+                // Pick up the whole stack
+                _ => {
+                    if let Some(stack) = stack {
+                        *floating_stack = FloatingStack {
+                            slot: Some(cell.id),
+                            nb: stack.nb,
+                            split: false,
+                        };
                     }
                 }
             }
         }
         // Welcome to nesting hell
         else if mouse_input.just_pressed(MouseButton::Right) {
-            // If floating stack exists : remove 1 item from floating stack
-            if floating_exists {
-                let floating_items = floating_items.unwrap();
-
-                if stack_exists {
-                    let stack = stack.unwrap();
-
-                    if floating_items.item_id == stack.item_id && floating_items.nb > 0 {
-                        // Get added nb of items into inventory -> removes them from floating stack
-
-                        remove_item_floating_stack(
-                            &mut floating_stack,
-                            inventory.add_item_to_stack(
-                                cell.id,
-                                1,
-                                floating_items.item_id,
-                                floating_items.item_type,
-                            ),
-                        );
-                    }
-                } else if floating_items.nb > 0 {
-                    // Get added nb of items into inventory -> removes them from floating stack
-                    remove_item_floating_stack(
-                        &mut floating_stack,
-                        inventory.add_item_to_stack(
-                            cell.id,
-                            1,
-                            floating_items.item_id,
-                            floating_items.item_type,
-                        ),
+            // If floating stack exists : put 1 item down in the cell
+            if let (Some(slot), Some(source)) = (floating_stack.slot, source) {
+                let has_room = stack.is_none_or(|stack| {
+                    stack.item_id == source.item_id && stack.nb < stack.item_id.get_max_stack()
+                });
+                if slot != cell.id && has_room {
+                    send_network_action(
+                        &mut client,
+                        NetworkAction::InventoryAction(InventoryAction::Move {
+                            from: slot,
+                            to: cell.id,
+                            nb: 1,
+                        }),
                     );
+                    put_down_floating_items(&mut floating_stack, 1);
                 }
             }
-            // Else if hovering a stack : cut hovered stack in half (rounded up), and push it to floating stack
-            else if stack_exists {
-                let stack = stack.unwrap();
-                let nb = (stack.nb + 1) / 2;
-                // Get removed nb of items removed from inventory -> adds them into the floating stack
-                add_item_floating_stack(
-                    &mut floating_stack,
-                    inventory.remove_item_from_stack(cell.id, nb),
-                    stack.item_id,
-                    stack.item_type,
-                );
+            // Else if hovering a stack : pick up half of the hovered stack (rounded up)
+            else if let Some(stack) = stack {
+                *floating_stack = FloatingStack {
+                    slot: Some(cell.id),
+                    nb: (stack.nb + 1) / 2,
+                    split: true,
+                };
             }
         } else {
             border_color.0 = Color::WHITE;
@@ -241,6 +272,15 @@ pub fn render_inventory_hotbar(
     }
 }
 
+/// Removes up to `nb` items from the floating stack, once they were sent to a cell
+fn put_down_floating_items(floating_stack: &mut FloatingStack, nb: u32) {
+    floating_stack.nb -= nb.min(floating_stack.nb);
+    floating_stack.split = false;
+    if floating_stack.nb == 0 {
+        *floating_stack = FloatingStack::default();
+    }
+}
+
 pub fn update_inventory_cell(
     stack: &Option<shared::world::ItemStack>,
     txt: &mut Text,
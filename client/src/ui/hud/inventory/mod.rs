@@ -21,10 +21,15 @@ pub struct InventoryCell {
     pub id: u32,
 }
 
-/// The current selected stack, not considered in the player's inventory
-#[derive(Component)]
+/// Items picked up with the mouse\
+/// They stay in their slot on the server until they are put down somewhere else
+#[derive(Component, Default)]
 pub struct FloatingStack {
-    pub items: Option<ItemStack>,
+    /// Slot the items were picked up from
+    pub slot: Option<u32>,
+    pub nb: u32,
+    /// Half of the stack picked up with the right button, put down with `InventoryAction::Split`
+    pub split: bool,
 }
 
 #[derive(PartialEq, Eq, Clone, Copy, Resource)]
@@ -44,10 +49,7 @@ pub fn set_ui_mode(mut ui_mode: ResMut<UIMode>, visibility: Query<&Visibility, W
 }
 
 mod display;
-pub mod items;
 mod setup;
 
 pub use display::*;
-use items::*;
 pub use setup::*;
-use shared::world::ItemStack;
@@ -1,12 +1,12 @@
 use super::UiDialog;
 use crate::constants::{
-    HOTBAR_BORDER, HOTBAR_CELL_SIZE, HOTBAR_PADDING, MAX_HOTBAR_SLOTS, MAX_INVENTORY_SLOTS,
-    TEXTURE_SIZE,
+    HOTBAR_BORDER, HOTBAR_CELL_SIZE, HOTBAR_PADDING, MAX_HOTBAR_SLOTS, TEXTURE_SIZE,
 };
 use crate::ui::hud::{FloatingStack, InventoryCell, InventoryDialog, InventoryRoot};
 use crate::world::MaterialResource;
 use crate::GameState;
 use bevy::{prelude::*, ui::FocusPolicy};
+use shared::players::MAX_INVENTORY_SLOTS;
 
 pub fn setup_inventory(
     mut commands: Commands,
@@ -59,6 +59,8 @@ pub fn setup_inventory(
     let dialog = commands
         .spawn((
             InventoryDialog,
+            // Detects clicks on the dialog, which do not drop the floating stack
+            Interaction::default(),
             NodeBundle {
                 background_color: BackgroundColor(Color::srgb(0.4, 0.4, 0.4)),
                 border_radius: BorderRadius::all(Val::Percent(10.)),
@@ -162,7 +164,7 @@ pub fn setup_inventory(
 
     let floating_stack = commands
         .spawn((
-            FloatingStack { items: None },
+            FloatingStack::default(),
             NodeBundle {
                 focus_policy: FocusPolicy::Pass,
                 style: Style {
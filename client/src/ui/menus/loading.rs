@@ -1,6 +1,13 @@
+use crate::network::{ConnectionError, TargetServer, TargetServerState};
 use crate::GameState;
 use bevy::prelude::*;
 
+/// Time during which the reason of a failed connection is displayed, in seconds
+const CONNECTION_ERROR_DISPLAY_TIME: f32 = 4.0;
+
+#[derive(Component)]
+pub struct LoadingText;
+
 // Spawns the necessary components for the loading screen.
 pub fn load_loading_screen(mut commands: Commands) {
     let text_style = TextStyle {
@@ -37,9 +44,40 @@ pub fn load_loading_screen(mut commands: Commands) {
             },
         ))
         .with_children(|parent| {
-            parent.spawn(TextBundle::from_sections([TextSection::new(
-                "Loading...",
-                text_style.clone(),
-            )]));
+            parent.spawn((
+                TextBundle::from_sections([TextSection::new("Loading...", text_style.clone())]),
+                LoadingText,
+            ));
         });
 }
+
+// Displays why the connection failed, then goes back to the menu
+pub fn display_connection_error(
+    connection_error: Res<ConnectionError>,
+    mut target: ResMut<TargetServer>,
+    mut texts: Query<&mut Text, With<LoadingText>>,
+    mut game_state: ResMut<NextState<GameState>>,
+    time: Res<Time>,
+    mut display_timer: Local<Option<Timer>>,
+) {
+    if target.state != TargetServerState::Refused {
+        return;
+    }
+    let Some(reason) = &connection_error.reason else {
+        return;
+    };
+
+    let timer = display_timer.get_or_insert_with(|| {
+        for mut text in texts.iter_mut() {
+            text.sections[0].value = format!("Connection failed\n{}", reason);
+            text.sections[0].style.font_size = 30.0;
+        }
+        Timer::from_seconds(CONNECTION_ERROR_DISPLAY_TIME, TimerMode::Once)
+    });
+
+    if timer.tick(time.delta()).finished() {
+        *display_timer = None;
+        target.state = TargetServerState::Initial;
+        game_state.set(GameState::Menu);
+    }
+}
@@ -17,6 +17,7 @@ use controls::{controls_menu_setup, controls_update_system};
 use multi::multiplayer_action;
 
 use crate::input::keyboard::save_keybindings;
+use crate::network::ConnectionError;
 use crate::{DisplayQuality, GameState, MenuCamera, Volume};
 
 use super::button::*;
@@ -79,13 +80,23 @@ pub fn menu_plugin(app: &mut App) {
 }
 
 /// Tag component for scrolling UI lists
-fn menu_setup(mut menu_state: ResMut<NextState<MenuState>>, mut commands: Commands) {
+fn menu_setup(
+    mut menu_state: ResMut<NextState<MenuState>>,
+    mut commands: Commands,
+    connection_error: Res<ConnectionError>,
+) {
     commands.spawn((
         Camera2dBundle::default(),
         MenuCamera,
         StateScoped(GameState::Menu),
     ));
-    menu_state.set(MenuState::Main);
+
+    // Go back to the server list to display why the connection failed
+    if connection_error.reason.is_some() {
+        menu_state.set(MenuState::Multi);
+    } else {
+        menu_state.set(MenuState::Main);
+    }
 }
 
 fn menu_action(
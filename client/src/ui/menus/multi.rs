@@ -1,6 +1,6 @@
 use super::{MenuButtonAction, MenuState, ScrollingList};
 use crate::constants::SERVER_LIST_SAVE_NAME;
-use crate::network::{TargetServer, TargetServerState};
+use crate::network::{ConnectionError, TargetServer, TargetServerState};
 use crate::ui::assets::*;
 use crate::ui::style::*;
 use crate::GameState;
@@ -37,6 +37,9 @@ use std::{
 pub struct ServerItem {
     pub name: String,
     pub ip: String,
+    /// Connects without connect token, to servers which do not use the secure authentication
+    #[serde(default)]
+    pub unsecure: bool,
 }
 
 #[derive(Component, Default)]
@@ -47,6 +50,7 @@ pub struct ServerList {
 #[derive(Component)]
 pub enum MultiplayerButtonAction {
     Add,
+    ToggleUnsecure,
     Connect(Entity),
     Delete(Entity),
 }
@@ -57,10 +61,23 @@ pub struct ServerIpInput;
 #[derive(Component)]
 pub struct ServerNameInput;
 
+/// Whether the server being added is joined without connect token
+#[derive(Component, Default)]
+pub struct UnsecureToggle(pub bool);
+
+fn unsecure_toggle_label(unsecure: bool) -> &'static str {
+    if unsecure {
+        "No connect token (unsecure)"
+    } else {
+        "Connect token required"
+    }
+}
+
 pub fn multiplayer_menu_setup(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     _paths: Res<GameFolderPaths>,
+    mut connection_error: ResMut<ConnectionError>,
 ) {
     let font = load_font(&asset_server);
     let background_image = load_background_image(&asset_server);
@@ -109,6 +126,17 @@ pub fn multiplayer_menu_setup(
                 ..Default::default()
             });
 
+            // Displayed only once, after a failed connection
+            if let Some(reason) = connection_error.reason.take() {
+                root.spawn(TextBundle {
+                    text: Text::from_section(
+                        format!("Connection failed: {}", reason),
+                        text_style(font.clone(), 18.0, Color::srgb(0.9, 0.2, 0.2)),
+                    ),
+                    ..Default::default()
+                });
+            }
+
             root.spawn(NodeBundle {
                 border_color: BorderColor(BACKGROUND_COLOR),
                 style: Style {
@@ -219,6 +247,31 @@ pub fn multiplayer_menu_setup(
                         });
                     });
 
+                wrapper
+                    .spawn((
+                        ButtonBundle {
+                            border_color: BorderColor(Color::BLACK),
+                            background_color: BackgroundColor(BACKGROUND_COLOR),
+                            style: {
+                                let mut style = btn_style.clone();
+                                style.grid_column = GridPlacement::span(2);
+                                style
+                            },
+                            ..Default::default()
+                        },
+                        MultiplayerButtonAction::ToggleUnsecure,
+                        UnsecureToggle::default(),
+                    ))
+                    .with_children(|btn| {
+                        btn.spawn(TextBundle {
+                            text: Text::from_section(
+                                unsecure_toggle_label(false),
+                                txt_style.clone(),
+                            ),
+                            ..Default::default()
+                        });
+                    });
+
                 wrapper
                     .spawn((
                         ButtonBundle {
@@ -246,13 +299,17 @@ pub fn multiplayer_menu_setup(
 pub fn add_server_item(
     name: String,
     ip: String,
+    unsecure: bool,
     commands: &mut Commands,
     asset_server: &Res<AssetServer>,
     list: &mut ServerList,
     list_entity: Entity,
     _paths: &Res<GameFolderPaths>,
 ) {
-    info!("Adding server to list : name = {:?}, ip = {:?}", name, ip);
+    info!(
+        "Adding server to list : name = {:?}, ip = {:?}, unsecure = {}",
+        name, ip, unsecure
+    );
 
     let btn_style = Style {
         display: Display::Flex,
@@ -335,7 +392,11 @@ pub fn add_server_item(
                         },
                     },
                     TextSection {
-                        value: ip.clone(),
+                        value: if unsecure {
+                            format!("{} (no connect token)", ip)
+                        } else {
+                            ip.clone()
+                        },
                         style: TextStyle {
                             font: asset_server.load("./fonts/RustCraftRegular-Bmg3.otf"),
                             font_size: 15.,
@@ -365,6 +426,7 @@ pub fn add_server_item(
         ServerItem {
             name: name.clone(),
             ip: ip.clone(),
+            unsecure,
         },
     );
 }
@@ -399,6 +461,8 @@ pub fn load_server_list(
         add_server_item(
             "localhost".into(),
             "127.0.0.1:8000".into(),
+            // Dedicated servers do not use the secure authentication by default
+            true,
             &mut commands,
             &assets,
             &mut list,
@@ -421,6 +485,8 @@ pub fn load_server_list(
         add_server_item(
             "localhost".into(),
             "127.0.0.1:8000".into(),
+            // Dedicated servers do not use the secure authentication by default
+            true,
             &mut commands,
             &assets,
             &mut list,
@@ -433,6 +499,7 @@ pub fn load_server_list(
         add_server_item(
             srv.name,
             srv.ip,
+            srv.unsecure,
             &mut commands,
             &assets,
             &mut list,
@@ -498,6 +565,8 @@ pub fn multiplayer_action(
         Query<&TextInputValue, (With<ServerNameInput>, Without<ServerIpInput>)>,
         Query<&TextInputValue, (With<ServerIpInput>, Without<ServerNameInput>)>,
         Query<(Entity, &mut ServerList), With<ServerList>>,
+        Query<(&mut UnsecureToggle, &Children)>,
+        Query<&mut Text>,
     ),
     mut commands: Commands,
     asset_server: Res<AssetServer>,
@@ -506,7 +575,8 @@ pub fn multiplayer_action(
     mut menu_state: ResMut<NextState<MenuState>>,
     paths: Res<GameFolderPaths>,
 ) {
-    let (interaction_query, name_query, ip_query, mut list_query) = queries;
+    let (interaction_query, name_query, ip_query, mut list_query, mut toggle_query, mut texts) =
+        queries;
     if list_query.is_empty() {
         return;
     }
@@ -520,10 +590,12 @@ pub fn multiplayer_action(
                     if !name_query.is_empty() && !ip_query.is_empty() {
                         let name = name_query.single();
                         let ip = ip_query.single();
+                        let unsecure = toggle_query.iter().any(|(toggle, _)| toggle.0);
 
                         add_server_item(
                             name.0.clone(),
                             ip.0.clone(),
+                            unsecure,
                             &mut commands,
                             &asset_server,
                             &mut list,
@@ -532,6 +604,16 @@ pub fn multiplayer_action(
                         );
                     }
                 }
+                MultiplayerButtonAction::ToggleUnsecure => {
+                    for (mut toggle, children) in toggle_query.iter_mut() {
+                        toggle.0 = !toggle.0;
+                        for child in children.iter() {
+                            if let Ok(mut text) = texts.get_mut(*child) {
+                                text.sections[0].value = unsecure_toggle_label(toggle.0).into();
+                            }
+                        }
+                    }
+                }
                 MultiplayerButtonAction::Connect(serv_entity) => {
                     if let Some(srv) = list.servers.get(&serv_entity) {
                         info!("Server : name={}, ip={}", srv.name, srv.ip);
@@ -540,6 +622,8 @@ pub fn multiplayer_action(
                         // TODO: Recover from another place
                         target_server.address = Some(srv.ip.parse().unwrap());
                         target_server.state = TargetServerState::Initial;
+                        target_server.is_solo = false;
+                        target_server.unsecure = srv.unsecure;
                         game_state.set(GameState::PreGameLoading);
                         menu_state.set(MenuState::Disabled);
                     }
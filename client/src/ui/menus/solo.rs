@@ -251,21 +251,33 @@ pub fn list_worlds(
 
     let paths = fs::read_dir(path).unwrap();
 
+    // Each world has its own folder, older saves are a single `<world>.ron` file
+    // which gets converted by the server when the world is loaded
+    let mut world_names: Vec<String> = Vec::new();
     for path in paths {
-        let path_str = path.unwrap().file_name().into_string().unwrap();
-
-        if path_str.ends_with(".ron") {
-            add_world_item(
-                path_str.replace(".ron", ""),
-                &mut commands,
-                &assets,
-                &mut list,
-                list_entity,
-                &mut world_map,
-                &game_paths,
-            );
+        let entry = path.unwrap();
+        let path_str = entry.file_name().into_string().unwrap();
+
+        if entry.path().is_dir() {
+            world_names.push(path_str);
+        } else if let Some(name) = path_str.strip_suffix(".ron") {
+            world_names.push(name.to_string());
         }
     }
+    world_names.sort();
+    world_names.dedup();
+
+    for name in world_names {
+        add_world_item(
+            name,
+            &mut commands,
+            &assets,
+            &mut list,
+            list_entity,
+            &mut world_map,
+            &game_paths,
+        );
+    }
 }
 
 fn add_world_item(
@@ -483,20 +495,28 @@ pub fn delete_save_files(
     world_name: &str,
     game_folder_path: &Res<GameFolderPaths>,
 ) -> Result<(), io::Error> {
-    // Delete `world_save.ron`
-    match fs::remove_file(format!(
-        "{}{}.ron",
-        get_game_folder(Some(game_folder_path))
-            .join(SAVE_PATH)
-            .display(),
-        world_name
-    )) {
+    let save_path: PathBuf = get_game_folder(Some(game_folder_path)).join(SAVE_PATH);
+
+    // Delete the world folder (level data and region files)
+    match fs::remove_dir_all(save_path.join(world_name)) {
         Ok(_) => info!("Successfully deleted world"),
         Err(ref e) if e.kind() == io::ErrorKind::NotFound => {
-            error!("world_save.ron not found, skipping.")
+            info!("World folder not found, skipping.")
         }
         Err(e) => error!("Failed to delete world: {}", e),
     }
 
+    // Delete the save of the former format, if any
+    for legacy_file in [
+        format!("{world_name}.ron"),
+        format!("{world_name}.ron.migrated"),
+    ] {
+        match fs::remove_file(save_path.join(legacy_file)) {
+            Ok(_) => info!("Successfully deleted legacy world save"),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => error!("Failed to delete legacy world save: {}", e),
+        }
+    }
+
     Ok(())
 }
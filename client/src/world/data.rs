@@ -1,5 +1,6 @@
 use bevy::prelude::*;
 use shared::world::BlockData;
+use shared::world::ChunkStorage;
 use std::collections::HashSet;
 use std::hash::Hash;
 
@@ -9,6 +10,7 @@ use serde::{Deserialize, Serialize};
 use shared::world::block_to_chunk_coord;
 use shared::world::global_block_to_chunk_pos;
 use shared::world::to_local_pos;
+use shared::world::WorldMap;
 use shared::CHUNK_SIZE;
 use std::collections::HashMap;
 
@@ -20,7 +22,7 @@ pub enum GlobalMaterial {
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct ClientChunk {
-    pub map: HashMap<IVec3, BlockData>, // Maps block positions within a chunk to block IDs
+    pub blocks: ChunkStorage, // Blocks of the chunk, indexed by local position
     #[serde(skip)]
     pub entity: Option<Entity>,
 }
@@ -33,6 +35,12 @@ pub struct ClientWorldMap {
     pub total_chunks_count: u64,
 }
 
+impl WorldMap for ClientWorldMap {
+    fn get_block_by_coordinates(&self, position: &IVec3) -> Option<&BlockData> {
+        ClientWorldMap::get_block_by_coordinates(self, position)
+    }
+}
+
 impl ClientWorldMap {
     pub fn get_block_by_coordinates(&self, position: &IVec3) -> Option<&BlockData> {
         let x: i32 = position.x;
@@ -47,7 +55,7 @@ impl ClientWorldMap {
                 let sub_x: i32 = ((x % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
                 let sub_y: i32 = ((y % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
                 let sub_z: i32 = ((z % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
-                chunk.map.get(&IVec3::new(sub_x, sub_y, sub_z))
+                chunk.blocks.get(&IVec3::new(sub_x, sub_y, sub_z))
             }
             None => None,
         }
@@ -65,7 +73,7 @@ impl ClientWorldMap {
 
         let local_block_pos: IVec3 = to_local_pos(global_block_pos);
 
-        chunk_map.map.remove(&local_block_pos);
+        chunk_map.blocks.remove(&local_block_pos);
 
         Some(kind)
     }
@@ -82,7 +90,7 @@ impl ClientWorldMap {
         let sub_y: i32 = ((y % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
         let sub_z: i32 = ((z % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
 
-        chunk.map.insert(IVec3::new(sub_x, sub_y, sub_z), block);
+        chunk.blocks.insert(IVec3::new(sub_x, sub_y, sub_z), block);
     }
 }
 
@@ -7,7 +7,9 @@ use bevy::{
     prelude::*,
     render::mesh::{Indices, PrimitiveTopology},
 };
-use shared::world::{to_global_pos, BlockDirection, BlockId, BlockTransparency};
+use shared::world::{
+    is_in_chunk, to_global_pos, BlockData, BlockDirection, BlockId, BlockTransparency,
+};
 
 use super::voxel::{Face, FaceDirection, VoxelShape};
 
@@ -41,15 +43,21 @@ pub(crate) fn generate_chunk_mesh(
 
     let mut indices_offset = 0;
 
-    for (local_block_pos, block) in chunk.map.iter() {
+    for (local_block_pos, block) in chunk.blocks.iter() {
         let x = local_block_pos.x as f32;
         let y = local_block_pos.y as f32;
         let z = local_block_pos.z as f32;
 
-        let global_block_pos = &to_global_pos(chunk_pos, local_block_pos);
         let visibility = block.id.get_visibility();
 
-        if is_block_surrounded(world_map, global_block_pos, &visibility, &block.id) {
+        if is_block_surrounded(
+            world_map,
+            chunk,
+            chunk_pos,
+            &local_block_pos,
+            &visibility,
+            &block.id,
+        ) {
             continue;
         }
 
@@ -59,7 +67,16 @@ pub(crate) fn generate_chunk_mesh(
         let mut local_uvs: Vec<[f32; 2]> = vec![];
         let mut local_colors: Vec<[f32; 4]> = vec![];
 
-        let voxel = VoxelShape::create_from_block(block);
+        let mut voxel = VoxelShape::create_from_block(block);
+
+        // The surface of a liquid is lowered, unless the same liquid flows above it
+        if visibility == BlockTransparency::Liquid {
+            let above =
+                get_neighbor_block(world_map, chunk, chunk_pos, &(local_block_pos + IVec3::Y));
+            if !above.is_some_and(|above| above.id == block.id) {
+                voxel = voxel.with_height(block.liquid_height());
+            }
+        }
 
         for face in voxel.faces.iter() {
             let uv_coords: &UvCoords;
@@ -70,7 +87,14 @@ pub(crate) fn generate_chunk_mesh(
                 uv_coords = block_uvs.get("_Default").unwrap();
             }
 
-            if should_render_face(world_map, global_block_pos, &face.direction, &visibility) {
+            if should_render_face(
+                world_map,
+                chunk,
+                chunk_pos,
+                &local_block_pos,
+                &face.direction,
+                &visibility,
+            ) {
                 render_face(
                     &mut local_vertices,
                     &mut local_indices,
@@ -118,17 +142,34 @@ pub(crate) fn generate_chunk_mesh(
     mesh
 }
 
+/// Returns the block at a position relative to the chunk being meshed\
+/// Neighbors inside the chunk are read directly from its storage, without any map lookup
+fn get_neighbor_block<'a>(
+    world_map: &'a ClientWorldMap,
+    chunk: &'a ClientChunk,
+    chunk_pos: &IVec3,
+    local_pos: &IVec3,
+) -> Option<&'a BlockData> {
+    if is_in_chunk(local_pos) {
+        chunk.blocks.get(local_pos)
+    } else {
+        world_map.get_block_by_coordinates(&to_global_pos(chunk_pos, local_pos))
+    }
+}
+
 pub(crate) fn is_block_surrounded(
     world_map: &ClientWorldMap,
-    global_block_pos: &IVec3,
+    chunk: &ClientChunk,
+    chunk_pos: &IVec3,
+    local_block_pos: &IVec3,
     block_visibility: &BlockTransparency,
     block_id: &BlockId,
 ) -> bool {
     for offset in &shared::world::SIX_OFFSETS {
-        let neighbor_pos = *global_block_pos + *offset;
+        let neighbor_pos = *local_block_pos + *offset;
 
         // Check if the block exists at the neighboring position
-        if let Some(block) = world_map.get_block_by_coordinates(&neighbor_pos) {
+        if let Some(block) = get_neighbor_block(world_map, chunk, chunk_pos, &neighbor_pos) {
             let vis = block.id.get_visibility();
             match vis {
                 BlockTransparency::Solid => {}
@@ -196,7 +237,9 @@ fn render_face(
 
 fn should_render_face(
     world_map: &ClientWorldMap,
-    global_block_pos: &IVec3,
+    chunk: &ClientChunk,
+    chunk_pos: &IVec3,
+    local_block_pos: &IVec3,
     direction: &FaceDirection,
     block_visibility: &BlockTransparency,
 ) -> bool {
@@ -210,7 +253,9 @@ fn should_render_face(
         FaceDirection::Inset => return true,
     };
 
-    if let Some(block) = world_map.get_block_by_coordinates(&(*global_block_pos + offset)) {
+    if let Some(block) =
+        get_neighbor_block(world_map, chunk, chunk_pos, &(*local_block_pos + offset))
+    {
         let vis = block.id.get_visibility();
         match vis {
             BlockTransparency::Solid => false,
@@ -67,7 +67,7 @@ fn update_chunk(
         let ch = world_map.map.get_mut(chunk_pos).unwrap();
         ch.entity = Some(new_entity);
     }
-    // debug!("ClientChunk updated : len={}", chunk.map.len());
+    // debug!("ClientChunk updated : len={}", chunk.blocks.len());
 }
 
 pub fn world_render_system(
@@ -101,24 +101,29 @@ pub fn world_render_system(
         // Using a set so same chunks are not reloaded multiple times
         // Accumulate chunks to render
         for event in &events {
-            let target_chunk_pos = match event {
-                WorldRenderRequestUpdateEvent::ChunkToReload(pos) => pos,
+            match event {
+                WorldRenderRequestUpdateEvent::ChunkToReload(pos) => {
+                    chunks_to_reload.insert(*pos);
+                    for offset in &SIX_OFFSETS {
+                        chunks_to_reload.insert(*pos + *offset);
+                    }
+                }
                 WorldRenderRequestUpdateEvent::BlockToReload(pos) => {
-                    // Temporary shortcut
-                    &global_block_to_chunk_pos(pos)
+                    // Neighbouring chunks only need to be reloaded when the block touches them
+                    let chunk_pos = global_block_to_chunk_pos(pos);
+                    chunks_to_reload.insert(chunk_pos);
+                    for offset in &SIX_OFFSETS {
+                        let neighbor_chunk_pos = global_block_to_chunk_pos(&(*pos + *offset));
+                        chunks_to_reload.insert(neighbor_chunk_pos);
+                    }
                 }
-            };
-
-            chunks_to_reload.insert(*target_chunk_pos);
-            for offset in &SIX_OFFSETS {
-                chunks_to_reload.insert(*target_chunk_pos + *offset);
             }
         }
 
         for pos in chunks_to_reload {
             if let Some(chunk) = world_map.map.get(&pos) {
                 // If chunk is empty, ignore it
-                if chunk.map.is_empty() {
+                if chunk.blocks.is_empty() {
                     continue;
                 }
 
@@ -85,10 +85,29 @@ impl VoxelShape {
                 shape
             }
             BlockId::Poppy | BlockId::Dandelion => Self::flora(block),
+            BlockId::Water => {
+                let mut shape = Self::full_cube(block);
+                for face in shape.faces.iter_mut() {
+                    face.texture = "WaterStill".into();
+                }
+                shape
+            }
             _ => Self::full_cube(block),
         }
     }
 
+    /// Lowers the top of the shape to the given height, used for the surface of liquids
+    pub fn with_height(mut self, height: f32) -> Self {
+        for face in self.faces.iter_mut() {
+            for vertex in face.vertices.iter_mut() {
+                if vertex[1] == 1. {
+                    vertex[1] = height;
+                }
+            }
+        }
+        self
+    }
+
     pub fn full_cube(block: &BlockData) -> Self {
         VoxelShape {
             faces: vec![
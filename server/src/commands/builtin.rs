@@ -0,0 +1,332 @@
+use super::{
+    find_player, parse_arg, permission_level, Command, CommandRegistry, CommandResult,
+    CommandSender, PermissionLevel,
+};
+use crate::init::{ServerLobby, ServerTime, TickCounter};
+use crate::network::broadcast_chat::{ChatHistory, SERVER_AUTHOR_NAME};
+use crate::network::disconnect::PendingDisconnects;
+use crate::world::player_data::PlayerDataStore;
+use crate::world::save::SaveRequestEvent;
+use bevy::diagnostic::{DiagnosticsStore, FrameTimeDiagnosticsPlugin};
+use bevy::prelude::*;
+use bevy_renet::renet::ClientId;
+use shared::messages::PlayerId;
+use shared::players::MAX_INVENTORY_SLOTS;
+use shared::world::{ItemId, ItemStack, ServerWorldMap, WorldSeed};
+
+pub fn register_builtin_commands(registry: &mut CommandRegistry) {
+    registry.register(Command {
+        name: "help",
+        usage: "[command]",
+        help: "Lists the available commands",
+        permission: PermissionLevel::Player,
+        handler: help,
+    });
+    registry.register(Command {
+        name: "list",
+        usage: "",
+        help: "Lists the players online",
+        permission: PermissionLevel::Player,
+        handler: list,
+    });
+    registry.register(Command {
+        name: "seed",
+        usage: "",
+        help: "Shows the seed of the world",
+        permission: PermissionLevel::Player,
+        handler: seed,
+    });
+    registry.register(Command {
+        name: "tp",
+        usage: "[player] <x> <y> <z> | [player] <target>",
+        help: "Teleports a player to a position or to another player",
+        permission: PermissionLevel::Operator,
+        handler: tp,
+    });
+    registry.register(Command {
+        name: "give",
+        usage: "<player> <item> [count]",
+        help: "Gives items to a player",
+        permission: PermissionLevel::Operator,
+        handler: give,
+    });
+    registry.register(Command {
+        name: "time",
+        usage: "[set <seconds>]",
+        help: "Shows or changes the time of the day",
+        permission: PermissionLevel::Operator,
+        handler: time,
+    });
+    registry.register(Command {
+        name: "kick",
+        usage: "<player> [reason]",
+        help: "Disconnects a player",
+        permission: PermissionLevel::Operator,
+        handler: kick,
+    });
+    registry.register(Command {
+        name: "save",
+        usage: "",
+        help: "Saves the world",
+        permission: PermissionLevel::Operator,
+        handler: save,
+    });
+    registry.register(Command {
+        name: "say",
+        usage: "<message>",
+        help: "Sends a message to every player",
+        permission: PermissionLevel::Operator,
+        handler: say,
+    });
+    registry.register(Command {
+        name: "tps",
+        usage: "",
+        help: "Shows the number of ticks per second",
+        permission: PermissionLevel::Operator,
+        handler: tps,
+    });
+    registry.register(Command {
+        name: "stop",
+        usage: "",
+        help: "Saves the world and stops the server",
+        permission: PermissionLevel::Operator,
+        handler: stop,
+    });
+}
+
+fn sender_player(sender: CommandSender) -> Result<PlayerId, String> {
+    match sender {
+        CommandSender::Player(id) => Ok(id),
+        CommandSender::Console => Err("the console must name a player".into()),
+    }
+}
+
+fn help(world: &mut World, sender: CommandSender, args: &[&str]) -> CommandResult {
+    let level = permission_level(world, sender);
+    let registry = world.resource::<CommandRegistry>();
+
+    if let Some(name) = args.first() {
+        let name = name.trim_start_matches('/');
+        return match registry.get(name) {
+            Some(command) if command.permission <= level => Ok(format!(
+                "/{} {} : {}",
+                command.name, command.usage, command.help
+            )),
+            _ => Err(format!("unknown command /{}", name)),
+        };
+    }
+
+    let names: Vec<String> = registry
+        .iter()
+        .filter(|command| command.permission <= level)
+        .map(|command| format!("/{}", command.name))
+        .collect();
+    Ok(format!(
+        "Commands: {} (/help <command> for details)",
+        names.join(", ")
+    ))
+}
+
+fn list(world: &mut World, _sender: CommandSender, _args: &[&str]) -> CommandResult {
+    let lobby = world.resource::<ServerLobby>();
+    let mut names: Vec<&str> = lobby
+        .players
+        .values()
+        .map(|player| player.name.as_str())
+        .collect();
+    names.sort();
+    Ok(format!(
+        "{} players online: {}",
+        names.len(),
+        names.join(", ")
+    ))
+}
+
+fn seed(world: &mut World, _sender: CommandSender, _args: &[&str]) -> CommandResult {
+    Ok(format!("Seed: {}", world.resource::<WorldSeed>().0))
+}
+
+fn tp(world: &mut World, sender: CommandSender, args: &[&str]) -> CommandResult {
+    // The player is optional, the sender teleports itself by default
+    let (player_id, destination) = match args.len() {
+        1 => (sender_player(sender)?, player_position(world, args[0])?),
+        2 => (
+            find_player(world, args[0])?,
+            player_position(world, args[1])?,
+        ),
+        3 => (sender_player(sender)?, parse_position(args)?),
+        4 => (find_player(world, args[0])?, parse_position(&args[1..])?),
+        _ => return Err("wrong number of arguments".into()),
+    };
+
+    let mut lobby = world.resource_mut::<ServerLobby>();
+    let Some(player) = lobby.players.get_mut(&player_id) else {
+        return Err("the player is not in the game".into());
+    };
+    player.vertical_velocity = 0.0;
+    let player_name = player.name.clone();
+
+    // The client of the player is corrected by the next world update
+    world
+        .resource_mut::<ServerWorldMap>()
+        .player_positions
+        .insert(player_id, destination);
+
+    Ok(format!(
+        "Teleported {} to {:.1} {:.1} {:.1}",
+        player_name, destination.x, destination.y, destination.z
+    ))
+}
+
+fn parse_position(args: &[&str]) -> Result<Vec3, String> {
+    Ok(Vec3::new(
+        parse_arg(args, 0, "x")?,
+        parse_arg(args, 1, "y")?,
+        parse_arg(args, 2, "z")?,
+    ))
+}
+
+fn player_position(world: &World, name: &str) -> Result<Vec3, String> {
+    let id = find_player(world, name)?;
+    world
+        .resource::<ServerWorldMap>()
+        .player_positions
+        .get(&id)
+        .copied()
+        .ok_or_else(|| format!("{} has not spawned yet", name))
+}
+
+/// Reads an item name such as `oak_log` or `OakLog`
+fn parse_item(name: &str) -> Result<ItemId, String> {
+    let variant: String = name
+        .split('_')
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    ron::from_str::<ItemId>(&variant).map_err(|_| format!("unknown item {}", name))
+}
+
+fn give(world: &mut World, _sender: CommandSender, args: &[&str]) -> CommandResult {
+    let player_name: String = parse_arg(args, 0, "player")?;
+    let player_id = find_player(world, &player_name)?;
+    let item_id = parse_item(args.get(1).ok_or("missing argument <item>")?)?;
+    let count: u32 = if args.len() > 2 {
+        parse_arg(args, 2, "count")?
+    } else {
+        1
+    };
+    if count == 0 {
+        return Err("the count must be positive".into());
+    }
+    // More items than a whole inventory can hold would be lost anyway
+    let max_count = MAX_INVENTORY_SLOTS * item_id.get_max_stack();
+    if count > max_count {
+        return Err(format!("the count must be at most {}", max_count));
+    }
+
+    let stack = ItemStack {
+        item_id,
+        item_type: item_id.get_default_type(),
+        nb: count,
+    };
+    let mut player_data = world.resource_mut::<PlayerDataStore>();
+    let Some(inventory) = player_data.inventory_mut(player_id) else {
+        return Err("the player is not in the game".into());
+    };
+    let lost = inventory.add_item_to_inventory(stack);
+    if lost == count {
+        return Err(format!("the inventory of {} is full", player_name));
+    }
+
+    Ok(format!(
+        "Gave {} {:?} to {}",
+        count - lost,
+        item_id,
+        player_name
+    ))
+}
+
+fn time(world: &mut World, _sender: CommandSender, args: &[&str]) -> CommandResult {
+    match args.first() {
+        None => return Ok(format!("Time: {}", world.resource::<ServerTime>().0)),
+        Some(&"set") => {}
+        Some(_) => return Err("unknown subcommand".into()),
+    }
+    let seconds: u64 = parse_arg(args, 1, "seconds")?;
+    world.resource_mut::<ServerTime>().0 = seconds;
+    Ok(format!("Time set to {}", seconds))
+}
+
+fn kick(world: &mut World, _sender: CommandSender, args: &[&str]) -> CommandResult {
+    let player_name: String = parse_arg(args, 0, "player")?;
+    let player_id = find_player(world, &player_name)?;
+    let reason = if args.len() > 1 {
+        args[1..].join(" ")
+    } else {
+        "Kicked by an operator".into()
+    };
+
+    // The player is told why before being disconnected
+    let client_id = ClientId::from_raw(player_id);
+    let event = world
+        .resource_mut::<ChatHistory>()
+        .private_server_message(player_id, format!("You were kicked: {}", reason));
+    world.send_event(event);
+    let tick = world.resource::<TickCounter>().tick;
+    world
+        .resource_mut::<PendingDisconnects>()
+        .schedule(client_id, tick);
+
+    Ok(format!("Kicked {}: {}", player_name, reason))
+}
+
+fn save(world: &mut World, _sender: CommandSender, _args: &[&str]) -> CommandResult {
+    world.send_event(SaveRequestEvent);
+    Ok("Saving the world".into())
+}
+
+fn say(world: &mut World, sender: CommandSender, args: &[&str]) -> CommandResult {
+    if args.is_empty() {
+        return Err("missing argument <message>".into());
+    }
+    let author_name = match sender {
+        CommandSender::Player(id) => world
+            .resource::<ServerLobby>()
+            .players
+            .get(&id)
+            .map(|player| player.name.clone())
+            .unwrap_or_else(|| SERVER_AUTHOR_NAME.into()),
+        CommandSender::Console => SERVER_AUTHOR_NAME.into(),
+    };
+
+    let event = world
+        .resource_mut::<ChatHistory>()
+        .push(author_name, args.join(" "));
+    world.send_event(event);
+    Ok(String::new())
+}
+
+fn tps(world: &mut World, _sender: CommandSender, _args: &[&str]) -> CommandResult {
+    // The server runs one tick per frame
+    let tps = world
+        .resource::<DiagnosticsStore>()
+        .get(&FrameTimeDiagnosticsPlugin::FPS)
+        .and_then(|diagnostic| diagnostic.smoothed());
+    match tps {
+        Some(tps) => Ok(format!("TPS: {:.1}", tps)),
+        None => Err("no measure yet".into()),
+    }
+}
+
+fn stop(world: &mut World, _sender: CommandSender, _args: &[&str]) -> CommandResult {
+    info!("Server is going down...");
+    // The world is saved later in this tick, before the app exits
+    world.send_event(SaveRequestEvent);
+    world.send_event(AppExit::Success);
+    Ok("Server stopped".into())
+}
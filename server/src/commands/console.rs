@@ -0,0 +1,50 @@
+use super::{execute_commands, CommandEvent, CommandSender};
+use bevy::prelude::*;
+use std::io::{self, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+
+/// Lines typed in the terminal, read by a separate thread so that the server never waits for them
+#[derive(Resource)]
+pub struct ConsoleInput {
+    receiver: Mutex<Receiver<String>>,
+}
+
+/// Reads commands from stdin, such as `save`, `stop`, `list` or `kick <player>`\
+/// Only used by dedicated servers, a solo world shares the terminal of the game
+pub fn setup_console(app: &mut App) {
+    let (sender, receiver) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                break;
+            };
+            // The server stopped
+            if sender.send(line).is_err() {
+                break;
+            }
+        }
+    });
+
+    app.insert_resource(ConsoleInput {
+        receiver: Mutex::new(receiver),
+    });
+    app.add_systems(Update, read_console_input.before(execute_commands));
+}
+
+fn read_console_input(console: Res<ConsoleInput>, mut ev_command: EventWriter<CommandEvent>) {
+    let receiver = console.receiver.lock().unwrap();
+    while let Ok(line) = receiver.try_recv() {
+        // The slash of the chat commands is optional
+        let line = line.trim().trim_start_matches('/');
+        if line.is_empty() {
+            continue;
+        }
+        ev_command.send(CommandEvent {
+            sender: CommandSender::Console,
+            line: line.to_string(),
+        });
+    }
+}
@@ -0,0 +1,198 @@
+mod builtin;
+mod console;
+
+use crate::init::ServerLobby;
+use crate::network::auth::load_usernames;
+use crate::network::broadcast_chat::ChatHistory;
+use bevy::prelude::*;
+use shared::messages::PlayerId;
+use shared::GameServerConfig;
+use std::collections::{BTreeMap, HashSet};
+use std::path::Path;
+use std::str::FromStr;
+
+pub use builtin::register_builtin_commands;
+pub use console::setup_console;
+
+pub const OPERATORS_FILE_NAME: &str = "operators.ron";
+
+/// Required to run a command, ordered from the lowest to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum PermissionLevel {
+    Player,
+    Operator,
+    Console,
+}
+
+/// Who runs a command, the reply is sent back to it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CommandSender {
+    Player(PlayerId),
+    /// Commands typed in the terminal running the server
+    Console,
+}
+
+/// Reply sent to the sender, an error when the command could not be run
+pub type CommandResult = Result<String, String>;
+
+pub type CommandHandler = fn(&mut World, CommandSender, &[&str]) -> CommandResult;
+
+#[derive(Clone, Copy)]
+pub struct Command {
+    pub name: &'static str,
+    /// Arguments, shown in the help and when they cannot be parsed
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub permission: PermissionLevel,
+    pub handler: CommandHandler,
+}
+
+#[derive(Resource, Default)]
+pub struct CommandRegistry {
+    commands: BTreeMap<&'static str, Command>,
+}
+
+impl CommandRegistry {
+    pub fn register(&mut self, command: Command) {
+        if self.commands.insert(command.name, command).is_some() {
+            warn!("Command /{} registered twice", command.name);
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<&Command> {
+        self.commands.get(name)
+    }
+
+    /// Commands sorted by name
+    pub fn iter(&self) -> impl Iterator<Item = &Command> {
+        self.commands.values()
+    }
+}
+
+/// Usernames allowed to run the operator commands, read from `operators.ron` in the world folder
+#[derive(Resource, Default, Debug)]
+pub struct Operators {
+    pub usernames: HashSet<String>,
+}
+
+impl Operators {
+    pub fn load(world_folder: &Path) -> Self {
+        Self {
+            usernames: load_usernames(world_folder, OPERATORS_FILE_NAME),
+        }
+    }
+}
+
+/// A chat line starting with `/`, without it
+#[derive(Event, Debug)]
+pub struct CommandEvent {
+    pub sender: CommandSender,
+    pub line: String,
+}
+
+pub fn setup_commands(app: &mut App) {
+    let mut registry = CommandRegistry::default();
+    register_builtin_commands(&mut registry);
+
+    app.insert_resource(registry);
+    app.add_event::<CommandEvent>();
+}
+
+/// Runs the commands received since the last tick\
+/// Commands can touch any part of the server, so they are given the whole world
+pub fn execute_commands(world: &mut World) {
+    let events: Vec<CommandEvent> = world
+        .resource_mut::<Events<CommandEvent>>()
+        .drain()
+        .collect();
+
+    for event in events {
+        info!("Command from {:?}: /{}", event.sender, event.line);
+        let reply = match run_command(world, event.sender, &event.line) {
+            Ok(reply) => reply,
+            Err(e) => format!("Error: {}", e),
+        };
+        if !reply.is_empty() {
+            send_reply(world, event.sender, reply);
+        }
+    }
+}
+
+fn run_command(world: &mut World, sender: CommandSender, line: &str) -> CommandResult {
+    let mut parts = line.split_whitespace();
+    let Some(name) = parts.next() else {
+        return Err("empty command".into());
+    };
+    let args: Vec<&str> = parts.collect();
+
+    let Some(command) = world.resource::<CommandRegistry>().get(name).copied() else {
+        return Err(format!("unknown command /{}, type /help", name));
+    };
+    if permission_level(world, sender) < command.permission {
+        return Err(format!("you are not allowed to use /{}", name));
+    }
+
+    (command.handler)(world, sender, &args)
+        .map_err(|e| format!("{} (usage: /{} {})", e, command.name, command.usage))
+}
+
+pub fn permission_level(world: &World, sender: CommandSender) -> PermissionLevel {
+    match sender {
+        CommandSender::Player(id) => {
+            // Whoever plays a solo world owns it
+            if world.resource::<GameServerConfig>().is_solo {
+                return PermissionLevel::Operator;
+            }
+            let is_operator = world
+                .resource::<ServerLobby>()
+                .players
+                .get(&id)
+                .is_some_and(|player| {
+                    world
+                        .resource::<Operators>()
+                        .usernames
+                        .contains(&player.name)
+                });
+            if is_operator {
+                PermissionLevel::Operator
+            } else {
+                PermissionLevel::Player
+            }
+        }
+        CommandSender::Console => PermissionLevel::Console,
+    }
+}
+
+/// Sends a private message from the server to the sender of a command, or prints it in the console
+pub fn send_reply(world: &mut World, sender: CommandSender, reply: String) {
+    match sender {
+        CommandSender::Player(id) => {
+            // Sent after the messages created before it, see `ChatHistory`
+            let event = world
+                .resource_mut::<ChatHistory>()
+                .private_server_message(id, reply);
+            world.send_event(event);
+        }
+        CommandSender::Console => println!("{}", reply),
+    }
+}
+
+/// Parses the argument at `index`, the error names the expected argument
+pub fn parse_arg<T: FromStr>(args: &[&str], index: usize, name: &str) -> Result<T, String> {
+    let arg = args
+        .get(index)
+        .ok_or_else(|| format!("missing argument <{}>", name))?;
+    arg.parse()
+        .map_err(|_| format!("invalid value for <{}>: {}", name, arg))
+}
+
+/// Finds a logged in player by name
+pub fn find_player(world: &World, name: &str) -> Result<PlayerId, String> {
+    world
+        .resource::<ServerLobby>()
+        .players
+        .values()
+        .find(|player| player.name == name)
+        .map(|player| player.id)
+        .ok_or_else(|| format!("no player named {}", name))
+}
@@ -0,0 +1,89 @@
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use shared::players::GameMode;
+use std::fs;
+use std::path::Path;
+
+pub const SERVER_SETTINGS_FILE_NAME: &str = "server.ron";
+
+/// Parameters of the server, read from `server.ron` in the game folder by dedicated servers\
+/// Missing fields take their default value, and the command line flags override the file
+#[derive(Resource, Serialize, Deserialize, Debug, Clone)]
+#[serde(default)]
+pub struct ServerSettings {
+    /// 0 picks any free port
+    pub port: u16,
+    /// Maximum number of players logged in at the same time
+    pub max_players: usize,
+    /// Ticks per second
+    pub tick_rate: u32,
+    /// Render distance requested by the clients is capped to this value, in chunks
+    pub max_render_distance: u32,
+    /// Seconds between two automatic saves, 0 disables them
+    pub autosave_interval: u64,
+    /// Number of previous saves kept in the `backups` folder of the world, 0 disables them
+    pub backup_count: usize,
+    /// Minimum number of seconds between two backups, made after a save, 0 disables them
+    pub backup_interval: u64,
+    pub spawn_point: Vec3,
+    /// Message of the day, sent to the players when they join
+    pub motd: String,
+    /// Only the usernames listed in `whitelist.ron` can log in
+    pub whitelist: bool,
+    /// Game mode of the players joining the server
+    pub default_game_mode: GameMode,
+}
+
+impl Default for ServerSettings {
+    fn default() -> Self {
+        Self {
+            port: 0,
+            max_players: 32,
+            tick_rate: 60,
+            max_render_distance: 16,
+            autosave_interval: 300,
+            backup_count: 3,
+            backup_interval: 3600,
+            spawn_point: Vec3::new(0.0, 80.0, 0.0),
+            motd: String::new(),
+            whitelist: false,
+            default_game_mode: GameMode::default(),
+        }
+    }
+}
+
+impl ServerSettings {
+    /// Reads the settings, or writes the default ones if the file does not exist yet
+    pub fn load_or_create(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        if !path.exists() {
+            let settings = Self::default();
+            settings.write(path)?;
+            println!("Default server settings written to {:?}", path);
+            return Ok(settings);
+        }
+
+        let settings: Self = ron::from_str(&fs::read_to_string(path)?)?;
+        settings.validate()?;
+        Ok(settings)
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let serialized = ron::ser::to_string_pretty(self, PrettyConfig::new())?;
+        fs::write(path, serialized)?;
+        Ok(())
+    }
+
+    pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.max_players == 0 {
+            return Err("max_players must be at least 1".into());
+        }
+        if self.tick_rate == 0 {
+            return Err("tick_rate must be at least 1".into());
+        }
+        if self.max_render_distance == 0 {
+            return Err("max_render_distance must be at least 1".into());
+        }
+        Ok(())
+    }
+}
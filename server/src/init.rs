@@ -1,4 +1,9 @@
+use crate::commands::{setup_console, Operators};
+use crate::config::ServerSettings;
+use crate::network::auth::{BannedPlayers, Whitelist};
 use crate::network::dispatcher::{self, setup_resources_and_events};
+use crate::network::token_service::spawn_token_service;
+use crate::shutdown::setup_shutdown_signal;
 use bevy::{
     diagnostic::{FrameTimeDiagnosticsPlugin, LogDiagnosticsPlugin},
     prelude::*,
@@ -8,12 +13,21 @@ use bevy_renet::renet::transport::NetcodeServerTransport;
 use bevy_renet::renet::RenetServer;
 use bevy_renet::RenetServerPlugin;
 use serde::{Deserialize, Serialize};
-use shared::{get_shared_renet_config, messages::PlayerId, GameFolderPaths, GameServerConfig};
+use shared::players::Player;
+use shared::{
+    get_shared_renet_config, messages::PlayerId, GameFolderPaths, GameServerConfig,
+    SecureAuthConfig, TOKEN_SERVICE_PORT_OFFSET,
+};
 use std::fmt::Debug;
 use std::time::{Duration, SystemTime};
 use std::{collections::HashMap, net::IpAddr};
 
+use crate::world::data::get_world_folder;
+use crate::world::fluids::FluidTicks;
+use crate::world::generation::PendingBlocks;
 use crate::world::load_from_file::{load_world_map, load_world_seed, load_world_time};
+use crate::world::player_data::PlayerDataStore;
+use crate::world::region::RegionStorage;
 
 use bevy_renet::renet::transport::{ServerAuthentication, ServerConfig};
 use bevy_renet::transport::NetcodeServerPlugin;
@@ -24,7 +38,7 @@ pub struct ServerTime(pub u64);
 
 #[derive(Debug, Default, Resource)]
 pub struct ServerLobby {
-    pub players: HashMap<PlayerId, String>,
+    pub players: HashMap<PlayerId, Player>,
 }
 
 #[allow(dead_code)]
@@ -37,22 +51,57 @@ pub fn acquire_socket_by_port(ip: IpAddr, port: u16) -> UdpSocket {
     UdpSocket::bind(addr).unwrap()
 }
 
-pub fn add_netcode_network(app: &mut App, socket: UdpSocket) {
+/// Connections accepted beyond the maximum number of players, so that refused clients can be told why
+const EXTRA_NETCODE_CLIENTS: usize = 32;
+
+pub fn add_netcode_network(
+    app: &mut App,
+    socket: UdpSocket,
+    secure: Option<&SecureAuthConfig>,
+    max_players: usize,
+) {
     app.add_plugins(NetcodeServerPlugin);
 
     let server = RenetServer::new(get_shared_renet_config());
 
     let granted_addr = &socket.local_addr().unwrap();
 
+    // Solo worlds are only reachable locally, they do not need connect tokens
+    let (public_addresses, authentication) = match secure {
+        Some(secure) => {
+            if secure.run_token_service {
+                match granted_addr.port().checked_add(TOKEN_SERVICE_PORT_OFFSET) {
+                    Some(port) => spawn_token_service(
+                        SocketAddr::new(granted_addr.ip(), port),
+                        secure.private_key,
+                        vec![secure.public_address],
+                    ),
+                    None => error!(
+                        "No port left for the token service after port {}, use a lower server port",
+                        granted_addr.port()
+                    ),
+                }
+            }
+            info!("Secure authentication enabled");
+            (
+                vec![secure.public_address],
+                ServerAuthentication::Secure {
+                    private_key: secure.private_key,
+                },
+            )
+        }
+        None => (vec![*granted_addr], ServerAuthentication::Unsecure),
+    };
+
     let current_time: Duration = SystemTime::now()
         .duration_since(SystemTime::UNIX_EPOCH)
         .unwrap();
     let server_config = ServerConfig {
         current_time,
-        max_clients: 64,
+        max_clients: max_players + EXTRA_NETCODE_CLIENTS,
         protocol_id: shared::PROTOCOL_ID,
-        public_addresses: vec![*granted_addr],
-        authentication: ServerAuthentication::Unsecure,
+        public_addresses,
+        authentication,
     };
 
     let transport = NetcodeServerTransport::new(server_config, socket).unwrap();
@@ -60,11 +109,16 @@ pub fn add_netcode_network(app: &mut App, socket: UdpSocket) {
     app.insert_resource(transport);
 }
 
-pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: String) {
+pub fn init(
+    socket: UdpSocket,
+    config: GameServerConfig,
+    settings: ServerSettings,
+    game_folder_path: String,
+) {
     let mut app = App::new();
     app.add_plugins(
         MinimalPlugins.set(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
-            1.0 / 60.0,
+            1.0 / settings.tick_rate as f64,
         ))),
     );
 
@@ -80,12 +134,19 @@ pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: Strin
     });
 
     let world_name = &config.world_name.clone();
-
-    app.insert_resource(config);
+    let is_solo = config.is_solo;
 
     info!("Starting server on {}", socket.local_addr().unwrap());
 
-    add_netcode_network(&mut app, socket);
+    add_netcode_network(
+        &mut app,
+        socket,
+        config.secure.as_ref(),
+        settings.max_players,
+    );
+
+    app.insert_resource(config);
+    app.insert_resource(settings);
 
     setup_resources_and_events(&mut app);
 
@@ -117,6 +178,16 @@ pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: Strin
         }
     };
 
+    // Chunks are loaded lazily from the region files of the world
+    let world_folder = get_world_folder(app.world().resource::<GameFolderPaths>(), world_name);
+    app.insert_resource(RegionStorage::new(&world_folder));
+    app.insert_resource(PlayerDataStore::new(&world_folder));
+    app.insert_resource(BannedPlayers::load(&world_folder));
+    app.insert_resource(Whitelist::load(&world_folder));
+    app.insert_resource(Operators::load(&world_folder));
+    app.insert_resource(PendingBlocks::load(&world_folder));
+    app.insert_resource(FluidTicks::default());
+
     // Insert world_map and seed into ressources
     app.insert_resource(world_map);
     app.insert_resource(world_seed);
@@ -124,6 +195,11 @@ pub fn init(socket: UdpSocket, config: GameServerConfig, game_folder_path: Strin
 
     dispatcher::register_systems(&mut app);
 
+    if !is_solo {
+        setup_console(&mut app);
+        setup_shutdown_signal(&mut app);
+    }
+
     setup_heartbeat(&mut app);
 
     app.run();
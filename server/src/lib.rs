@@ -1,7 +1,11 @@
+mod commands;
+mod config;
 mod init;
 mod network;
 mod player;
+mod shutdown;
 pub mod time;
 mod world;
 
+pub use config::ServerSettings;
 pub use init::{acquire_local_ephemeral_udp_socket, init};
@@ -1,40 +1,183 @@
-use std::net::Ipv4Addr;
+use std::fs;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::Path;
 
+use crate::config::{ServerSettings, SERVER_SETTINGS_FILE_NAME};
 use crate::init::acquire_socket_by_port;
 use clap::Parser;
-use shared::GameServerConfig;
+use shared::players::GameMode;
+use shared::{GameServerConfig, SecureAuthConfig};
 
+mod commands;
+mod config;
 mod init;
 mod network;
 mod player;
+mod shutdown;
 mod time;
 mod world;
 
 #[derive(Parser, Debug)]
 #[command(version, about, long_about = None)]
 struct Args {
-    #[arg(short, long, default_value_t = 0)]
-    port: u16,
+    /// Overrides the settings read from server.ron
+    #[arg(short, long)]
+    port: Option<u16>,
+
+    #[arg(long)]
+    max_players: Option<usize>,
+
+    #[arg(long)]
+    tick_rate: Option<u32>,
+
+    #[arg(long)]
+    max_render_distance: Option<u32>,
+
+    /// Seconds between two automatic saves, 0 disables them
+    #[arg(long)]
+    autosave_interval: Option<u64>,
+
+    #[arg(long)]
+    backup_count: Option<usize>,
+
+    /// Minimum seconds between two backups, 0 disables them
+    #[arg(long)]
+    backup_interval: Option<u64>,
+
+    #[arg(long)]
+    motd: Option<String>,
+
+    #[arg(long)]
+    whitelist: Option<bool>,
+
+    /// survival or creative
+    #[arg(long, value_parser = parse_game_mode)]
+    game_mode: Option<GameMode>,
 
     #[arg(short, long, default_value = "default")]
     world: String,
 
     #[arg(short, long, default_value = "../")]
     game_folder_path: String,
+
+    /// Enables the secure authentication, with the private key stored in this file (created if missing)
+    #[arg(long)]
+    private_key_file: Option<String>,
+
+    /// Address the clients connect to, written in the connect tokens
+    #[arg(long)]
+    public_address: Option<SocketAddr>,
+
+    /// Runs a local token service, on the port of the server + 1
+    #[arg(long, default_value_t = false)]
+    token_service: bool,
+}
+
+fn parse_game_mode(value: &str) -> Result<GameMode, String> {
+    match value.to_lowercase().as_str() {
+        "survival" => Ok(GameMode::Survival),
+        "creative" => Ok(GameMode::Creative),
+        _ => Err(format!("unknown game mode: {}", value)),
+    }
+}
+
+/// Reads server.ron from the game folder, then applies the command line flags
+fn load_settings(args: &Args) -> ServerSettings {
+    let path = Path::new(&args.game_folder_path).join(SERVER_SETTINGS_FILE_NAME);
+    let mut settings = ServerSettings::load_or_create(&path)
+        .unwrap_or_else(|e| panic!("Failed to load the server settings from {:?}: {}", path, e));
+
+    if let Some(port) = args.port {
+        settings.port = port;
+    }
+    if let Some(max_players) = args.max_players {
+        settings.max_players = max_players;
+    }
+    if let Some(tick_rate) = args.tick_rate {
+        settings.tick_rate = tick_rate;
+    }
+    if let Some(max_render_distance) = args.max_render_distance {
+        settings.max_render_distance = max_render_distance;
+    }
+    if let Some(autosave_interval) = args.autosave_interval {
+        settings.autosave_interval = autosave_interval;
+    }
+    if let Some(backup_count) = args.backup_count {
+        settings.backup_count = backup_count;
+    }
+    if let Some(backup_interval) = args.backup_interval {
+        settings.backup_interval = backup_interval;
+    }
+    if let Some(motd) = &args.motd {
+        settings.motd = motd.clone();
+    }
+    if let Some(whitelist) = args.whitelist {
+        settings.whitelist = whitelist;
+    }
+    if let Some(game_mode) = args.game_mode {
+        settings.default_game_mode = game_mode;
+    }
+
+    settings
+        .validate()
+        .unwrap_or_else(|e| panic!("Invalid server settings: {}", e));
+    settings
+}
+
+/// Reads the hexadecimal private key of the server, or generates a new one
+fn load_or_create_private_key(path: &Path) -> Result<[u8; 32], Box<dyn std::error::Error>> {
+    if !path.exists() {
+        let key: [u8; 32] = rand::random();
+        let hex: String = key.iter().map(|byte| format!("{:02x}", byte)).collect();
+        fs::write(path, hex)?;
+        println!("New private key written to {:?}", path);
+        return Ok(key);
+    }
+
+    let hex = fs::read_to_string(path)?;
+    let hex = hex.trim();
+    if hex.len() != 64 {
+        return Err(format!(
+            "the private key must be 64 hexadecimal characters, got {}",
+            hex.len()
+        )
+        .into());
+    }
+    let mut key = [0u8; 32];
+    for (i, byte) in key.iter_mut().enumerate() {
+        *byte = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)?;
+    }
+    Ok(key)
 }
 
 fn main() {
     let args = Args::parse();
-    let socket = acquire_socket_by_port(std::net::IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), args.port);
+    let settings = load_settings(&args);
+    let socket = acquire_socket_by_port(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), settings.port);
 
     let game_folder_path = args.game_folder_path.clone();
 
+    let secure = args.private_key_file.map(|path| {
+        let private_key = load_or_create_private_key(Path::new(&path))
+            .unwrap_or_else(|e| panic!("Failed to load the private key from {}: {}", path, e));
+        let port = socket.local_addr().unwrap().port();
+        SecureAuthConfig {
+            private_key,
+            public_address: args
+                .public_address
+                .unwrap_or(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), port)),
+            run_token_service: args.token_service,
+        }
+    });
+
     init::init(
         socket,
         GameServerConfig {
             world_name: args.world,
             is_solo: false,
+            secure,
         },
+        settings,
         game_folder_path,
     );
 }
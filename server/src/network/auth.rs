@@ -0,0 +1,110 @@
+use crate::config::ServerSettings;
+use crate::init::ServerLobby;
+use crate::network::disconnect::PendingDisconnects;
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{
+    AuthRegisterRequest, AuthRegisterResponse, AuthRejectionReason, ServerToClientMessage,
+};
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+pub const BANNED_PLAYERS_FILE_NAME: &str = "banned_players.ron";
+pub const WHITELIST_FILE_NAME: &str = "whitelist.ron";
+
+/// Usernames which cannot log in, read from `banned_players.ron` in the world folder
+#[derive(Resource, Default, Debug)]
+pub struct BannedPlayers {
+    pub usernames: HashSet<String>,
+}
+
+impl BannedPlayers {
+    pub fn load(world_folder: &Path) -> Self {
+        Self {
+            usernames: load_usernames(world_folder, BANNED_PLAYERS_FILE_NAME),
+        }
+    }
+}
+
+/// Usernames allowed to log in when the whitelist is enabled, read from `whitelist.ron` in the world folder
+#[derive(Resource, Default, Debug)]
+pub struct Whitelist {
+    pub usernames: HashSet<String>,
+}
+
+impl Whitelist {
+    pub fn load(world_folder: &Path) -> Self {
+        Self {
+            usernames: load_usernames(world_folder, WHITELIST_FILE_NAME),
+        }
+    }
+}
+
+/// Reads a list of usernames stored in the world folder, empty if the file does not exist
+pub fn load_usernames(world_folder: &Path, file_name: &str) -> HashSet<String> {
+    let path = world_folder.join(file_name);
+    if !path.exists() {
+        return HashSet::new();
+    }
+
+    match fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|contents| ron::from_str::<Vec<String>>(&contents).map_err(|e| e.to_string()))
+    {
+        Ok(usernames) => {
+            info!("{} usernames loaded from {}", usernames.len(), file_name);
+            usernames.into_iter().collect()
+        }
+        Err(e) => {
+            error!("Failed to load usernames from {:?}: {}", path, e);
+            HashSet::new()
+        }
+    }
+}
+
+/// Checks whether a player can log in with this username
+pub fn check_auth_request(
+    request: &AuthRegisterRequest,
+    lobby: &ServerLobby,
+    banned_players: &BannedPlayers,
+    whitelist: &Whitelist,
+    settings: &ServerSettings,
+) -> Result<(), AuthRejectionReason> {
+    if banned_players.usernames.contains(&request.username) {
+        return Err(AuthRejectionReason::Banned);
+    }
+    if settings.whitelist && !whitelist.usernames.contains(&request.username) {
+        return Err(AuthRejectionReason::NotWhitelisted);
+    }
+    if lobby
+        .players
+        .values()
+        .any(|player| player.name == request.username)
+    {
+        return Err(AuthRejectionReason::UsernameTaken);
+    }
+    if lobby.players.len() >= settings.max_players {
+        return Err(AuthRejectionReason::ServerFull);
+    }
+    Ok(())
+}
+
+/// Tells the client why it cannot log in, then disconnects it
+pub fn reject_auth_request(
+    server: &mut RenetServer,
+    client_id: ClientId,
+    reason: AuthRejectionReason,
+    pending_disconnects: &mut PendingDisconnects,
+    tick: u64,
+) {
+    info!("Login rejected for client {}: {}", client_id, reason);
+    let payload = bincode::options()
+        .serialize(&ServerToClientMessage::AuthRegisterResponse(
+            AuthRegisterResponse::Rejected(reason),
+        ))
+        .unwrap();
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, payload);
+    pending_disconnects.schedule(client_id, tick);
+}
@@ -1,36 +1,121 @@
-use crate::network::dispatcher::BroadcastTimer;
+use crate::init::ServerLobby;
 use bevy::prelude::*;
-use bevy_renet::renet::{DefaultChannel, RenetServer};
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
 use bincode::Options;
-use shared::messages::ChatConversation;
+use shared::messages::{
+    ChatConversation, ChatMessage, ChatMessageId, PlayerId, ServerToClientMessage,
+};
 
+/// Name displayed as the author of the messages written by the server
+pub const SERVER_AUTHOR_NAME: &str = "Server";
+
+/// A new message, sent to every player, or only to `recipient` for private messages
 #[derive(Event)]
-pub struct ChatMessageEvent;
+pub struct ChatMessageEvent {
+    pub message: ChatMessage,
+    pub recipient: Option<PlayerId>,
+}
+
+/// Recent messages of the conversation\
+/// Ids are shared with the private messages, which go through the same events,
+/// so that each client receives them in increasing order
+#[derive(Resource, Default, Debug)]
+pub struct ChatHistory {
+    pub conversation: ChatConversation,
+    next_id: ChatMessageId,
+}
+
+impl ChatHistory {
+    fn new_message(&mut self, author_name: String, content: String) -> ChatMessage {
+        let date = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_millis() as u64;
+
+        let id = self.next_id;
+        self.next_id += 1;
+
+        ChatMessage {
+            id,
+            author_name,
+            date,
+            content,
+        }
+    }
+
+    /// Adds a message to the conversation\
+    /// The returned event must be sent for the message to be broadcast
+    pub fn push(&mut self, author_name: String, content: String) -> ChatMessageEvent {
+        let message = self.new_message(author_name, content);
+        self.conversation.push(message.clone());
+        ChatMessageEvent {
+            message,
+            recipient: None,
+        }
+    }
+
+    /// Adds a message written by the server to the conversation, such as join and leave notices
+    pub fn push_server_message(&mut self, content: String) -> ChatMessageEvent {
+        self.push(SERVER_AUTHOR_NAME.into(), content)
+    }
+
+    /// Creates a message written by the server for a single player, kept out of the conversation\
+    /// The returned event must be sent for the message to be delivered
+    pub fn private_server_message(
+        &mut self,
+        recipient: PlayerId,
+        content: String,
+    ) -> ChatMessageEvent {
+        ChatMessageEvent {
+            message: self.new_message(SERVER_AUTHOR_NAME.into(), content),
+            recipient: Some(recipient),
+        }
+    }
+}
 
 pub fn setup_chat_resources(app: &mut App) {
-    app.insert_resource(ChatConversation { ..default() });
+    app.insert_resource(ChatHistory::default());
     app.add_event::<ChatMessageEvent>();
 }
 
+/// Sends the recent messages to a player who just joined
+pub fn send_chat_history(server: &mut RenetServer, client_id: ClientId, history: &ChatHistory) {
+    let payload = bincode::options()
+        .serialize(&ServerToClientMessage::ChatHistory(
+            history.conversation.clone(),
+        ))
+        .unwrap();
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, payload);
+}
+
+/// Sends a message to a single player
+pub fn send_chat_message(server: &mut RenetServer, client_id: ClientId, message: ChatMessage) {
+    let payload = bincode::options()
+        .serialize(&ServerToClientMessage::ChatMessage(message))
+        .unwrap();
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, payload);
+}
+
+/// Sends each new message once, to the players who are logged in or to the recipient of a private message
 pub fn broadcast_chat_messages(
     mut server: ResMut<RenetServer>,
-    chat_messages: Res<ChatConversation>,
-    time: Res<Time>,
-    mut timer: ResMut<BroadcastTimer>,
+    lobby: Res<ServerLobby>,
     mut ev_chat: EventReader<ChatMessageEvent>,
 ) {
-    timer.timer.tick(time.delta());
-    if timer.timer.finished() || !ev_chat.is_empty() {
-        trace!(
-            "Broadcasting chat history, {} messages",
-            chat_messages.messages.len()
-        );
-        let cm: ChatConversation = chat_messages.into_inner().clone();
-        let serialized = bincode::options().serialize(&cm).unwrap();
-        trace!("world {:?}", cm);
-        trace!("serialized: {:?}", serialized);
-        server.broadcast_message(DefaultChannel::ReliableOrdered, serialized);
-        ev_chat.clear();
-        timer.timer.reset();
+    for ChatMessageEvent { message, recipient } in ev_chat.read() {
+        trace!("Broadcasting chat message {:?} to {:?}", message, recipient);
+        match recipient {
+            Some(player_id) => {
+                let client_id = ClientId::from_raw(*player_id);
+                if server.is_connected(client_id) {
+                    send_chat_message(&mut server, client_id, message.clone());
+                }
+            }
+            None => {
+                for player_id in lobby.players.keys() {
+                    send_chat_message(&mut server, ClientId::from_raw(*player_id), message.clone());
+                }
+            }
+        }
     }
 }
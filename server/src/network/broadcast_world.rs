@@ -1,15 +1,21 @@
+use crate::init::ServerLobby;
 use crate::init::ServerTime;
 use crate::init::TickCounter;
 use crate::network::utils::format_bytes;
-use crate::world::generation::generate_chunk;
+use crate::player::PlayerInputStates;
+use crate::world::generation::{apply_pending_blocks, generate_chunk, PendingBlocks};
+use crate::world::region::RegionStorage;
 use bevy::math::IVec3;
 use bevy::prelude::*;
 use bevy_ecs::system::ResMut;
 use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
 use bincode::Options;
-use shared::messages::{ServerToClientMessage, WorldUpdate};
-use shared::world::{chunk_in_radius, ServerChunk, ServerWorldMap};
-use std::collections::HashMap;
+use shared::messages::{
+    BlockChange, BlockChanges, PlayerId, PlayerMovementState, PlayerOrientation,
+    ServerToClientMessage, WorldUpdate,
+};
+use shared::world::{chunk_in_radius, global_block_to_chunk_pos, ServerChunk, ServerWorldMap};
+use std::collections::{HashMap, HashSet};
 
 use shared::world::data::WorldSeed;
 
@@ -21,49 +27,99 @@ pub struct WorldUpdateRequestEvent {
     pub player_chunk_position: IVec3,
 }
 
+/// Part of the world a client can see, it only receives the updates happening there
+#[derive(Debug, Default)]
+pub struct ClientView {
+    pub player_chunk_position: IVec3,
+    pub render_distance: u32,
+    /// Chunks sent to the client, or known to be empty
+    pub loaded_chunks: HashSet<IVec3>,
+}
+
+impl ClientView {
+    fn can_see(&self, chunk_pos: &IVec3) -> bool {
+        chunk_in_radius(
+            &self.player_chunk_position,
+            chunk_pos,
+            self.render_distance as i32,
+        )
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ClientViews {
+    pub clients: HashMap<ClientId, ClientView>,
+}
+
 pub fn send_world_update(
     mut server: ResMut<RenetServer>,
+    mut client_views: ResMut<ClientViews>,
     ticker: Res<TickCounter>,
+    time: Res<Time>,
     seed: Res<WorldSeed>,
     mut world_map: ResMut<ServerWorldMap>,
+    mut region_storage: ResMut<RegionStorage>,
+    mut pending_blocks: ResMut<PendingBlocks>,
     mut ev_update: EventReader<WorldUpdateRequestEvent>,
 ) {
     let mut chunks_to_update_count = 0;
     for event in ev_update.read() {
+        // The client unloads the chunks which left its render distance
+        let view = client_views.clients.entry(event.client).or_default();
+        view.player_chunk_position = event.player_chunk_position;
+        view.render_distance = event.render_distance;
+        view.loaded_chunks.retain(|chunk_pos| {
+            chunk_in_radius(
+                &event.player_chunk_position,
+                chunk_pos,
+                event.render_distance as i32,
+            )
+        });
+
         let payload = bincode::options()
             .serialize(&ServerToClientMessage::WorldUpdate(WorldUpdate {
                 tick: ticker.tick,
-                player_positions: world_map.player_positions.clone(),
+                // Positions are sent by broadcast_world_state
+                player_positions: HashMap::new(),
+                player_states: HashMap::new(),
+                player_orientations: HashMap::new(),
+                timestamp: time.elapsed_seconds_f64(),
                 new_map: {
                     let mut map: HashMap<IVec3, ServerChunk> = HashMap::new();
                     for c in event.chunks.iter() {
-                        if chunk_in_radius(
-                            &event.player_chunk_position,
-                            c,
-                            event.render_distance as i32,
-                        ) {
+                        if view.can_see(c) {
+                            view.loaded_chunks.insert(*c);
+
                             let chunk = world_map.map.get(c);
 
                             // If chunk already exists, transmit it to client
                             if let Some(chunk) = chunk {
-                                if chunk.map.is_empty() {
+                                if chunk.blocks.is_empty() {
                                     continue;
                                 }
 
                                 chunks_to_update_count += 1;
                                 map.insert(*c, chunk.clone());
+                            } else if let Some(chunk) = region_storage.load_chunk(c) {
+                                // If chunk was saved on disk, load it before transmitting it
+                                chunks_to_update_count += 1;
+                                map.insert(*c, chunk.clone());
+                                world_map.map.insert(*c, chunk);
                             } else {
                                 // If chunk does not exists, generate it before transmitting it
-                                let chunk = generate_chunk(*c, seed.0);
+                                let chunk = generate_chunk(*c, seed.0, &mut pending_blocks);
 
-                                // If chunk is empty, do not create it to prevent unnecessary data transmission
-                                if chunk.map.is_empty() {
+                                // If chunk is empty, keep it so structures of its neighbours can be placed in it,
+                                // but do not transmit it to prevent unnecessary data transmission
+                                if chunk.blocks.is_empty() {
+                                    world_map.map.insert(*c, chunk);
                                     continue;
                                 }
 
                                 chunks_to_update_count += 1;
                                 map.insert(*c, chunk.clone());
                                 world_map.map.insert(*c, chunk);
+                                region_storage.mark_dirty(c);
                             }
                         }
                     }
@@ -79,47 +135,115 @@ pub fn send_world_update(
             chunks_to_update_count,
             format_bytes(payload.len() as u64)
         );
-        server.send_message(event.client, DefaultChannel::ReliableUnordered, payload);
+        // Ordered with the block changes, so that a chunk never overwrites newer changes
+        server.send_message(event.client, DefaultChannel::ReliableOrdered, payload);
     }
+
+    // Structures crossing chunk borders may modify chunks generated before
+    apply_pending_blocks(&mut world_map, &mut region_storage, &mut pending_blocks);
 }
 
 pub fn broadcast_world_state(
     mut server: ResMut<RenetServer>,
     ticker: Res<TickCounter>,
     mut world_map: ResMut<ServerWorldMap>,
-    time: Res<ServerTime>,
+    (time, server_time): (Res<Time>, Res<ServerTime>),
+    lobby: Res<ServerLobby>,
+    input_states: Res<PlayerInputStates>,
+    mut client_views: ResMut<ClientViews>,
 ) {
-    if ticker.tick % 10 != 0 {
-        return;
-    }
-
     // Update time value in the "ServerWorldMap" ressource
-    world_map.time = time.0;
-
-    trace!("Broadcast world update");
-    let payload = bincode::options()
-        .serialize(&ServerToClientMessage::WorldUpdate(to_network(
-            &mut world_map,
-            ticker.tick,
-        )))
-        .unwrap();
-    server.broadcast_message(DefaultChannel::ReliableUnordered, payload);
-}
+    world_map.time = server_time.0;
+
+    client_views
+        .clients
+        .retain(|client_id, _| server.is_connected(*client_id));
+
+    let block_changes = std::mem::take(&mut world_map.block_changes);
+    let all_player_states = player_states(&lobby, &input_states);
+
+    for (client_id, view) in client_views.clients.iter() {
+        // Only the modified blocks are sent, full chunks are sent when a client loads them
+        let changes: Vec<BlockChange> = block_changes
+            .iter()
+            .filter(|change| {
+                view.loaded_chunks
+                    .contains(&global_block_to_chunk_pos(&change.position))
+            })
+            .copied()
+            .collect();
+
+        if !changes.is_empty() {
+            trace!("Sending {} block changes to {}", changes.len(), client_id);
+            let payload = bincode::options()
+                .serialize(&ServerToClientMessage::BlockChanges(BlockChanges {
+                    tick: ticker.tick,
+                    changes,
+                }))
+                .unwrap();
+            // Changes of a block must be applied in the order they happened
+            server.send_message(*client_id, DefaultChannel::ReliableOrdered, payload);
+        }
+
+        // Players out of the render distance of the client are not sent, it hides them after a while
+        let player_positions: HashMap<PlayerId, Vec3> = world_map
+            .player_positions
+            .iter()
+            .filter(|(id, position)| {
+                **id == client_id.raw()
+                    || view.can_see(&global_block_to_chunk_pos(&position.floor().as_ivec3()))
+            })
+            .map(|(id, position)| (*id, *position))
+            .collect();
+
+        // The current player already knows where it looks
+        let player_orientations: HashMap<PlayerId, PlayerOrientation> = player_positions
+            .keys()
+            .filter(|id| **id != client_id.raw())
+            .filter_map(|id| {
+                lobby
+                    .players
+                    .get(id)
+                    .map(|player| (*id, player.orientation))
+            })
+            .collect();
+
+        // Each client only needs its own movement state, to reconcile its predictions
+        let player_states: HashMap<PlayerId, PlayerMovementState> = all_player_states
+            .get(&client_id.raw())
+            .map(|state| (client_id.raw(), *state))
+            .into_iter()
+            .collect();
 
-fn to_network(world_map: &mut ServerWorldMap, tick: u64) -> WorldUpdate {
-    WorldUpdate {
-        tick,
-        player_positions: world_map.player_positions.clone(),
-        new_map: {
-            let mut m: HashMap<IVec3, ServerChunk> = HashMap::new();
-            // Only send chunks that must be updated
-            for v in world_map.chunks_to_update.iter() {
-                m.insert(*v, world_map.map.get(v).unwrap().clone());
-            }
-            // Chunks are up do date, clear the vector
-            world_map.chunks_to_update.clear();
-            m
-        },
-        time: world_map.time,
+        let payload = bincode::options()
+            .serialize(&ServerToClientMessage::WorldUpdate(WorldUpdate {
+                tick: ticker.tick,
+                new_map: HashMap::new(),
+                player_positions,
+                player_states,
+                player_orientations,
+                timestamp: time.elapsed_seconds_f64(),
+                time: world_map.time,
+            }))
+            .unwrap();
+        server.send_message(*client_id, DefaultChannel::ReliableUnordered, payload);
     }
 }
+
+/// Movement states of the players, matching the positions currently stored in the world map
+fn player_states(
+    lobby: &ServerLobby,
+    input_states: &PlayerInputStates,
+) -> HashMap<PlayerId, PlayerMovementState> {
+    lobby
+        .players
+        .iter()
+        .map(|(id, player)| {
+            let last_input_tick = input_states
+                .players
+                .get(id)
+                .map_or(0, |state| state.last_tick);
+            (*id, player.movement_state(last_input_tick))
+        })
+        .collect()
+}
@@ -0,0 +1,35 @@
+use crate::init::TickCounter;
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, RenetServer};
+use std::collections::HashMap;
+
+/// Number of ticks between a rejection and the disconnection, so that the client receives the reason
+const DISCONNECT_DELAY: u64 = 30;
+
+/// Rejected clients, indexed by the tick at which they will be disconnected
+#[derive(Resource, Default, Debug)]
+pub struct PendingDisconnects {
+    pub clients: HashMap<ClientId, u64>,
+}
+
+impl PendingDisconnects {
+    /// Disconnects the client a few ticks later, once the message explaining why was sent
+    pub fn schedule(&mut self, client_id: ClientId, tick: u64) {
+        self.clients.insert(client_id, tick + DISCONNECT_DELAY);
+    }
+}
+
+pub fn disconnect_rejected_clients(
+    mut server: ResMut<RenetServer>,
+    mut pending_disconnects: ResMut<PendingDisconnects>,
+    ticker: Res<TickCounter>,
+) {
+    pending_disconnects.clients.retain(|client_id, tick| {
+        if *tick > ticker.tick {
+            return true;
+        }
+        debug!("Disconnecting rejected client {}", client_id);
+        server.disconnect(*client_id);
+        false
+    });
+}
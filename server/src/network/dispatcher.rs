@@ -1,58 +1,105 @@
+use crate::commands::{execute_commands, setup_commands, CommandEvent, CommandSender};
+use crate::config::ServerSettings;
 use crate::init::{ServerLobby, TickCounter};
+use crate::network::auth::{check_auth_request, reject_auth_request, BannedPlayers, Whitelist};
 use crate::network::broadcast_chat::*;
 use crate::network::broadcast_world::WorldUpdateRequestEvent;
 use crate::network::broadcast_world::*;
-use crate::player::handle_player_inputs;
+use crate::network::disconnect::{disconnect_rejected_clients, PendingDisconnects};
+use crate::network::handshake::{check_handshake, send_handshake_response, HandshakenClients};
+use crate::network::inventory::{
+    handle_inventory_actions, send_inventory_updates, InventoryActionEvent,
+};
+use crate::network::token_service::username_from_user_data;
+use crate::player::{handle_player_inputs, PlayerInputStates, PlayerInputsEvent};
 use crate::time::update_server_time;
 use crate::world;
+use crate::world::player_data::PlayerDataStore;
 use crate::world::save::SaveRequestEvent;
 use crate::world::BlockInteractionEvent;
 use bevy::prelude::*;
+use bevy_renet::renet::transport::NetcodeServerTransport;
 use bevy_renet::renet::{DefaultChannel, RenetServer, ServerEvent};
 use bincode::Options;
 use shared::messages::{
-    AuthRegisterResponse, ChatConversation, ClientToServerMessage, PlayerSpawnEvent,
-    ServerToClientMessage,
+    AuthRegisterResponse, AuthRejectionReason, ClientToServerMessage, HandshakeResponse,
+    PlayerDespawnEvent, PlayerSpawnEvent, ServerToClientMessage,
 };
+use shared::players::Player;
 use shared::world::ServerWorldMap;
 use shared::GameServerConfig;
 
-#[derive(Resource)]
-pub struct BroadcastTimer {
-    pub timer: Timer,
-}
-
 pub fn setup_resources_and_events(app: &mut App) {
-    app.insert_resource(BroadcastTimer {
-        timer: Timer::from_seconds(2.0, TimerMode::Repeating),
-    })
-    .add_event::<WorldUpdateRequestEvent>()
-    .add_event::<SaveRequestEvent>()
-    .add_event::<BlockInteractionEvent>();
+    app.add_event::<WorldUpdateRequestEvent>()
+        .add_event::<SaveRequestEvent>()
+        .add_event::<BlockInteractionEvent>()
+        .add_event::<PlayerInputsEvent>()
+        .add_event::<InventoryActionEvent>()
+        .init_resource::<PlayerInputStates>()
+        .init_resource::<ClientViews>()
+        .init_resource::<HandshakenClients>()
+        .init_resource::<PendingDisconnects>();
 
     setup_chat_resources(app);
+    setup_commands(app);
 }
 
 pub fn register_systems(app: &mut App) {
-    app.add_systems(Update, server_update_system);
+    app.add_systems(Update, (server_update_system, handle_player_inputs).chain());
 
-    app.add_systems(Update, broadcast_chat_messages);
+    app.add_systems(
+        Update,
+        (execute_commands, broadcast_chat_messages)
+            .chain()
+            .after(server_update_system),
+    );
 
-    app.add_systems(Update, (broadcast_world_state, send_world_update));
+    app.add_systems(
+        Update,
+        disconnect_rejected_clients.after(server_update_system),
+    );
 
-    app.add_systems(Update, world::save::save_world_system);
-    app.add_systems(Update, world::handle_block_interactions);
+    app.add_systems(
+        Update,
+        (broadcast_world_state, send_world_update).after(handle_player_inputs),
+    );
+
+    app.add_systems(
+        Update,
+        (world::save::autosave_system, world::save::save_world_system)
+            .chain()
+            .after(execute_commands),
+    );
+    app.add_systems(
+        Update,
+        (
+            world::handle_block_interactions,
+            world::fluids::fluid_tick_system,
+        )
+            .chain(),
+    );
+
+    // Every change of the inventories is sent once, at the end of the tick
+    app.add_systems(
+        Update,
+        (
+            handle_inventory_actions.after(server_update_system),
+            send_inventory_updates
+                .after(handle_inventory_actions)
+                .after(execute_commands)
+                .after(world::handle_block_interactions),
+        ),
+    );
 
     app.add_systems(Update, update_server_time);
 }
 
 fn server_update_system(
     mut server_events: EventReader<ServerEvent>,
-    (mut server, mut chat_conversation, mut lobby, tick): (
+    (mut server, mut chat_history, mut lobby): (
         ResMut<RenetServer>,
-        ResMut<ChatConversation>,
+        ResMut<ChatHistory>,
         ResMut<ServerLobby>,
-        Res<TickCounter>,
     ),
     (
         mut ev_chat,
@@ -60,14 +107,33 @@ fn server_update_system(
         mut ev_world_update_request,
         mut ev_save_request,
         mut ev_block_interaction,
+        mut ev_player_inputs,
+        mut ev_command,
+        mut ev_inventory_action,
     ): (
         EventWriter<ChatMessageEvent>,
         EventWriter<AppExit>,
         EventWriter<WorldUpdateRequestEvent>,
         EventWriter<SaveRequestEvent>,
         EventWriter<BlockInteractionEvent>,
+        EventWriter<PlayerInputsEvent>,
+        EventWriter<CommandEvent>,
+        EventWriter<InventoryActionEvent>,
     ),
-    config: Res<GameServerConfig>,
+    (mut handshaken, mut pending_disconnects, ticker, banned_players, whitelist): (
+        ResMut<HandshakenClients>,
+        ResMut<PendingDisconnects>,
+        Res<TickCounter>,
+        Res<BannedPlayers>,
+        Res<Whitelist>,
+    ),
+    (mut input_states, mut client_views, mut player_data): (
+        ResMut<PlayerInputStates>,
+        ResMut<ClientViews>,
+        ResMut<PlayerDataStore>,
+    ),
+    (config, settings): (Res<GameServerConfig>, Res<ServerSettings>),
+    transport: Res<NetcodeServerTransport>,
     mut world_map: ResMut<ServerWorldMap>,
 ) {
     for event in server_events.read() {
@@ -78,6 +144,34 @@ fn server_update_system(
             }
             ServerEvent::ClientDisconnected { client_id, reason } => {
                 info!("Player {} disconnected: {}", client_id, reason);
+                handshaken.clients.remove(client_id);
+                pending_disconnects.clients.remove(client_id);
+                client_views.clients.remove(client_id);
+
+                let player_id = client_id.raw();
+                let position = world_map.player_positions.remove(&player_id);
+                input_states.players.remove(&player_id);
+
+                // Clients rejected before logging in were never announced
+                let Some(player) = lobby.players.remove(&player_id) else {
+                    continue;
+                };
+
+                if let Err(e) = player_data.save(&player, position) {
+                    error!("Failed to save the data of {}: {}", player.name, e);
+                }
+                player_data.unload(player_id);
+
+                let despawn_payload = bincode::options()
+                    .serialize(&ServerToClientMessage::PlayerDespawn(PlayerDespawnEvent {
+                        id: player_id,
+                    }))
+                    .unwrap();
+                server.broadcast_message(DefaultChannel::ReliableOrdered, despawn_payload);
+
+                ev_chat.send(
+                    chat_history.push_server_message(format!("{} left the game", player.name)),
+                );
             }
         }
     }
@@ -90,37 +184,151 @@ fn server_update_system(
                 Ok(msg) => msg,
                 Err(e) => {
                     error!("Failed to parse incoming message: {}", e);
+                    // A client built from another version may not even send a readable handshake
+                    if !handshaken.clients.contains(&client_id)
+                        && !pending_disconnects.clients.contains_key(&client_id)
+                    {
+                        send_handshake_response(
+                            &mut server,
+                            client_id,
+                            HandshakeResponse::Rejected {
+                                reason: "Incompatible client version".into(),
+                            },
+                            &mut handshaken,
+                            &mut pending_disconnects,
+                            ticker.tick,
+                        );
+                    }
                     continue;
                 }
             };
 
+            // Nothing but the handshake is accepted before it succeeded
+            if !handshaken.clients.contains(&client_id)
+                && !matches!(msg, ClientToServerMessage::Handshake(_))
+            {
+                warn!(
+                    "Ignoring message from client {} before handshake",
+                    client_id
+                );
+                if matches!(msg, ClientToServerMessage::AuthRegisterRequest(_)) {
+                    reject_auth_request(
+                        &mut server,
+                        client_id,
+                        AuthRejectionReason::VersionMismatch,
+                        &mut pending_disconnects,
+                        ticker.tick,
+                    );
+                }
+                continue;
+            }
+
             match msg {
-                ClientToServerMessage::AuthRegisterRequest(auth_req) => {
+                ClientToServerMessage::Handshake(request) => {
+                    info!("Handshake received from {}: {:?}", client_id, request);
+                    let response = check_handshake(&request);
+                    send_handshake_response(
+                        &mut server,
+                        client_id,
+                        response,
+                        &mut handshaken,
+                        &mut pending_disconnects,
+                        ticker.tick,
+                    );
+                }
+                ClientToServerMessage::AuthRegisterRequest(mut auth_req) => {
                     info!("Auth request received {:?}", auth_req);
 
-                    if lobby.players.values().any(|v| *v == auth_req.username) {
-                        debug!("Username already in map: {}", &auth_req.username);
-                        return;
+                    // With secure authentication, the username is the one written in the connect token
+                    if config.secure.is_some() {
+                        match transport
+                            .user_data(client_id)
+                            .and_then(|user_data| username_from_user_data(&user_data))
+                        {
+                            Some(username) => auth_req.username = username,
+                            None => {
+                                warn!("No username in the connect token of {}", client_id);
+                                reject_auth_request(
+                                    &mut server,
+                                    client_id,
+                                    AuthRejectionReason::InvalidToken,
+                                    &mut pending_disconnects,
+                                    ticker.tick,
+                                );
+                                continue;
+                            }
+                        }
+                    }
+
+                    if let Err(reason) = check_auth_request(
+                        &auth_req,
+                        &lobby,
+                        &banned_players,
+                        &whitelist,
+                        &settings,
+                    ) {
+                        reject_auth_request(
+                            &mut server,
+                            client_id,
+                            reason,
+                            &mut pending_disconnects,
+                            ticker.tick,
+                        );
+                        continue;
                     }
 
+                    // The player receives the recent messages, then every new one
+                    send_chat_history(&mut server, client_id, &chat_history);
+                    if !settings.motd.is_empty() {
+                        ev_chat.send(
+                            chat_history
+                                .private_server_message(client_id.raw(), settings.motd.clone()),
+                        );
+                    }
+
+                    // The player comes back where it left the world, with the same state
+                    let data = player_data
+                        .load(client_id.raw(), &auth_req.username, &settings)
+                        .clone();
+
                     // let new_session_token = generate_session_token();
-                    lobby
-                        .players
-                        .insert(client_id.raw(), auth_req.username.clone());
+                    let mut player = Player::new(client_id.raw(), auth_req.username.clone());
+                    player.game_mode = data.game_mode;
+                    player.is_flying = data.is_flying && data.game_mode.can_fly();
+                    player.orientation = data.orientation;
+                    player.spawn_point = data.spawn_point;
+                    lobby.players.insert(client_id.raw(), player);
                     debug!("New lobby : {:?}", lobby);
 
+                    ev_chat.send(
+                        chat_history
+                            .push_server_message(format!("{} joined the game", auth_req.username)),
+                    );
+
+                    // The server owns the position of the player from now on
+                    let spawn_position = data.position;
+                    world_map
+                        .player_positions
+                        .insert(client_id.raw(), spawn_position);
+
                     let spawn_message = PlayerSpawnEvent {
                         id: client_id.raw(),
                         name: auth_req.username,
-                        position: Vec3::new(0.0, 80.0, 0.0),
+                        position: spawn_position,
+                        orientation: data.orientation,
+                        game_mode: data.game_mode,
+                        spawn_point: data.spawn_point,
                     };
 
                     // TODO: add cleanup system if no heartbeat
-                    let msg = &(AuthRegisterResponse {
-                        username: spawn_message.name.clone(),
-                        session_token: client_id.raw() as u128,
-                        spawn_event: spawn_message.clone(),
-                    });
+                    let msg = &ServerToClientMessage::AuthRegisterResponse(
+                        AuthRegisterResponse::Accepted {
+                            username: spawn_message.name.clone(),
+                            session_token: client_id.raw() as u128,
+                            spawn_event: spawn_message.clone(),
+                            inventory: data.inventory,
+                        },
+                    );
                     let auth_response_payload = bincode::options().serialize(msg).unwrap();
 
                     server.send_message(
@@ -129,11 +337,18 @@ fn server_update_system(
                         auth_response_payload,
                     );
 
-                    for (id, name) in lobby.players.iter() {
+                    for (id, player) in lobby.players.iter() {
                         let spawn_message = PlayerSpawnEvent {
                             id: *id,
-                            name: name.into(),
-                            position: Vec3::new(0.0, 80.0, 0.0),
+                            name: player.name.clone(),
+                            position: world_map
+                                .player_positions
+                                .get(id)
+                                .copied()
+                                .unwrap_or(spawn_position),
+                            orientation: player.orientation,
+                            game_mode: player.game_mode,
+                            spawn_point: player.spawn_point,
                         };
 
                         let spawn_message_wrapped =
@@ -149,23 +364,43 @@ fn server_update_system(
                 }
                 ClientToServerMessage::ChatMessage(chat_msg) => {
                     info!("Chat message received: {:?}", &chat_msg);
-                    chat_conversation.messages.push(chat_msg);
-                    ev_chat.send(ChatMessageEvent);
+                    // The author is the name the player logged in with
+                    let Some(player) = lobby.players.get(&client_id.raw()) else {
+                        warn!(
+                            "Ignoring chat message from client {} not logged in",
+                            client_id
+                        );
+                        continue;
+                    };
+                    // Commands are run by the server, and never shown to the other players
+                    if let Some(line) = chat_msg.content.strip_prefix('/') {
+                        ev_command.send(CommandEvent {
+                            sender: CommandSender::Player(player.id),
+                            line: line.to_string(),
+                        });
+                        continue;
+                    }
+                    ev_chat.send(chat_history.push(player.name.clone(), chat_msg.content));
                 }
                 ClientToServerMessage::Exit(order) => {
                     debug!("Received shutdown order... {:?}", order);
                     // TODO: add permission checks
                     if config.is_solo {
+                        // The world is saved later in this tick, before the app exits
                         info!("Server is going down...");
+                        ev_save_request.send(SaveRequestEvent);
                         ev_app_exit.send(AppExit::Success);
                     } else {
+                        // The player is removed from the lobby when the disconnection event is received
                         server.disconnect(client_id);
-                        lobby.players.remove(&(order.session_token as u64));
                         info!("Player {:?} disconnected", client_id);
                     }
                 }
                 ClientToServerMessage::PlayerInputs(inputs) => {
-                    handle_player_inputs(inputs, &tick);
+                    ev_player_inputs.send(PlayerInputsEvent {
+                        player_id: client_id.raw(),
+                        inputs,
+                    });
                 }
                 ClientToServerMessage::SaveWorldRequest(save_req) => {
                     debug!(
@@ -188,15 +423,22 @@ fn server_update_system(
                         requested_chunks.len(),
                     );
                     ev_world_update_request.send(WorldUpdateRequestEvent {
-                        render_distance,
+                        render_distance: render_distance.min(settings.max_render_distance),
                         client: client_id,
                         chunks: requested_chunks,
                         player_chunk_position,
                     });
                 }
+                ClientToServerMessage::InventoryAction(action) => {
+                    ev_inventory_action.send(InventoryActionEvent {
+                        player_id: client_id.raw(),
+                        action,
+                    });
+                }
                 ClientToServerMessage::BlockInteraction {
                     position,
                     block_type,
+                    slot,
                 } => {
                     debug!(
                         "Block interaction received at {:?}: {:?}",
@@ -204,13 +446,12 @@ fn server_update_system(
                     );
 
                     ev_block_interaction.send(BlockInteractionEvent {
+                        player_id: client_id.raw(),
                         position,
                         block_type,
+                        slot,
                     });
                 }
-                ClientToServerMessage::SetPlayerPosition { position } => {
-                    world_map.player_positions.insert(client_id.raw(), position);
-                }
             }
         }
     }
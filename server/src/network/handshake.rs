@@ -0,0 +1,59 @@
+use crate::network::disconnect::PendingDisconnects;
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{
+    HandshakeRequest, HandshakeResponse, ServerToClientMessage, GAME_VERSION, PROTOCOL_VERSION,
+    SUPPORTED_FEATURES,
+};
+use std::collections::HashSet;
+
+/// Clients whose handshake was accepted, the only ones allowed to send other messages
+#[derive(Resource, Default, Debug)]
+pub struct HandshakenClients {
+    pub clients: HashSet<ClientId>,
+}
+
+/// Checks that the client speaks the same protocol as the server
+pub fn check_handshake(request: &HandshakeRequest) -> HandshakeResponse {
+    if request.protocol_version != PROTOCOL_VERSION {
+        return HandshakeResponse::Rejected {
+            reason: format!(
+                "Incompatible versions : server is {} (protocol {}), client is {} (protocol {})",
+                GAME_VERSION, PROTOCOL_VERSION, request.game_version, request.protocol_version
+            ),
+        };
+    }
+
+    HandshakeResponse::Accepted {
+        game_version: GAME_VERSION.to_string(),
+        features: request.features & SUPPORTED_FEATURES,
+    }
+}
+
+/// Sends the handshake response, and schedules the disconnection of the client if it was rejected
+pub fn send_handshake_response(
+    server: &mut RenetServer,
+    client_id: ClientId,
+    response: HandshakeResponse,
+    handshaken: &mut HandshakenClients,
+    pending_disconnects: &mut PendingDisconnects,
+    tick: u64,
+) {
+    match &response {
+        HandshakeResponse::Accepted { .. } => {
+            info!("Handshake accepted for client {}", client_id);
+            handshaken.clients.insert(client_id);
+        }
+        HandshakeResponse::Rejected { reason } => {
+            info!("Handshake rejected for client {}: {}", client_id, reason);
+            handshaken.clients.remove(&client_id);
+            pending_disconnects.schedule(client_id, tick);
+        }
+    }
+
+    let payload = bincode::options()
+        .serialize(&ServerToClientMessage::Handshake(response))
+        .unwrap();
+    server.send_message(client_id, DefaultChannel::ReliableOrdered, payload);
+}
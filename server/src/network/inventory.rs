@@ -0,0 +1,48 @@
+use crate::world::player_data::PlayerDataStore;
+use bevy::prelude::*;
+use bevy_renet::renet::{ClientId, DefaultChannel, RenetServer};
+use bincode::Options;
+use shared::messages::{PlayerId, ServerToClientMessage};
+use shared::players::InventoryAction;
+
+#[derive(Event, Debug)]
+pub struct InventoryActionEvent {
+    pub player_id: PlayerId,
+    pub action: InventoryAction,
+}
+
+/// Applies the actions of the players on their inventory\
+/// An invalid action is ignored, and the player receives its inventory again to undo it
+pub fn handle_inventory_actions(
+    mut player_data: ResMut<PlayerDataStore>,
+    mut events: EventReader<InventoryActionEvent>,
+) {
+    for event in events.read() {
+        let Some(inventory) = player_data.inventory_mut(event.player_id) else {
+            continue;
+        };
+        if let Err(e) = inventory.apply(event.action) {
+            warn!(
+                "Invalid inventory action {:?} from player {}: {}",
+                event.action, event.player_id, e
+            );
+        }
+    }
+}
+
+/// Sends their whole inventory to the players whose inventory changed during this tick
+pub fn send_inventory_updates(
+    mut server: ResMut<RenetServer>,
+    mut player_data: ResMut<PlayerDataStore>,
+) {
+    for (player_id, inventory) in player_data.take_changed_inventories() {
+        let payload = bincode::options()
+            .serialize(&ServerToClientMessage::InventoryUpdate(inventory))
+            .unwrap();
+        server.send_message(
+            ClientId::from_raw(player_id),
+            DefaultChannel::ReliableOrdered,
+            payload,
+        );
+    }
+}
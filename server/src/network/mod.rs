@@ -1,4 +1,9 @@
+pub mod auth;
 pub mod broadcast_chat;
 pub mod broadcast_world;
+pub mod disconnect;
 pub mod dispatcher;
+pub mod handshake;
+pub mod inventory;
+pub mod token_service;
 pub mod utils;
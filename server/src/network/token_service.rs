@@ -0,0 +1,104 @@
+use bevy::prelude::*;
+use bevy_renet::renet::transport::{ConnectToken, NETCODE_USER_DATA_BYTES};
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+/// Time during which a token can be used to connect, in seconds
+const TOKEN_EXPIRE_SECONDS: u64 = 300;
+/// Time without any packet before a client is disconnected, in seconds
+const TOKEN_TIMEOUT_SECONDS: i32 = 15;
+/// Usernames are stored in the user data of the connect tokens
+const MAX_USERNAME_LENGTH: usize = 32;
+
+/// Stores the username in the user data of a connect token, padded with zeros
+pub fn username_to_user_data(username: &str) -> [u8; NETCODE_USER_DATA_BYTES] {
+    let mut user_data = [0u8; NETCODE_USER_DATA_BYTES];
+    let bytes = username.as_bytes();
+    let len = bytes.len().min(NETCODE_USER_DATA_BYTES);
+    user_data[..len].copy_from_slice(&bytes[..len]);
+    user_data
+}
+
+/// Reads the username written in the connect token of a client
+pub fn username_from_user_data(user_data: &[u8; NETCODE_USER_DATA_BYTES]) -> Option<String> {
+    let len = user_data
+        .iter()
+        .position(|byte| *byte == 0)
+        .unwrap_or(user_data.len());
+    let username = String::from_utf8(user_data[..len].to_vec()).ok()?;
+    (!username.is_empty()).then_some(username)
+}
+
+/// Stand-in for an authentication service, run locally next to the server\
+/// A client sends its username on a single line, and receives a connect token for this server\
+/// Each connection is handled on its own thread, so that a slow client does not delay the others\
+/// A real service would check a password before issuing the token
+pub fn spawn_token_service(
+    addr: SocketAddr,
+    private_key: [u8; 32],
+    public_addresses: Vec<SocketAddr>,
+) {
+    let listener = match TcpListener::bind(addr) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!("Failed to start the token service on {}: {}", addr, e);
+            return;
+        }
+    };
+    info!("Token service listening on {}", addr);
+
+    thread::spawn(move || {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            let public_addresses = public_addresses.clone();
+            thread::spawn(move || {
+                if let Err(e) = issue_token(stream, &private_key, &public_addresses) {
+                    warn!("Failed to issue a connect token: {}", e);
+                }
+            });
+        }
+    });
+}
+
+fn issue_token(
+    mut stream: TcpStream,
+    private_key: &[u8; 32],
+    public_addresses: &[SocketAddr],
+) -> Result<(), Box<dyn std::error::Error>> {
+    stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+
+    // Longer lines are cut, and then rejected as too long
+    let mut username = String::new();
+    BufReader::new(&stream)
+        .take(MAX_USERNAME_LENGTH as u64 + 1)
+        .read_line(&mut username)?;
+    let username = username.trim();
+    if username.is_empty() || username.len() > MAX_USERNAME_LENGTH {
+        return Err(format!("invalid username: {:?}", username).into());
+    }
+
+    // The id is chosen by the service, clients can no longer pick the id of another player
+    let client_id: u64 = rand::random();
+    let current_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH)?;
+    let token = ConnectToken::generate(
+        current_time,
+        shared::PROTOCOL_ID,
+        TOKEN_EXPIRE_SECONDS,
+        client_id,
+        TOKEN_TIMEOUT_SECONDS,
+        public_addresses.to_vec(),
+        Some(&username_to_user_data(username)),
+        private_key,
+    )?;
+
+    let mut payload: Vec<u8> = Vec::new();
+    token.write(&mut payload)?;
+    stream.write_all(&payload)?;
+
+    info!("Connect token issued for {} (id {})", username, client_id);
+    Ok(())
+}
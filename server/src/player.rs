@@ -1,10 +1,98 @@
-use crate::init::TickCounter;
+use crate::init::ServerLobby;
 use bevy::prelude::*;
-use bevy_ecs::prelude::Res;
-use shared::messages::PlayerInputs;
+use shared::messages::{PlayerId, PlayerInputs, PlayerOrientation};
+use shared::players::simulate_player_movement;
+use shared::world::{global_block_to_chunk_pos, ServerWorldMap};
+use std::collections::HashMap;
 
-pub fn handle_player_inputs(player_inputs: PlayerInputs, ticker: &Res<TickCounter>) {
-    if ticker.tick % 60 == 0 {
-        trace!("Received inputs: {:?}", player_inputs);
+/// Longest frame accepted from a client, in seconds
+const MAX_INPUT_DELTA: f32 = 0.1;
+/// Movement time a player can accumulate in advance, to absorb network jitter
+const MAX_TIME_BUDGET: f32 = 0.5;
+
+#[derive(Event, Debug)]
+pub struct PlayerInputsEvent {
+    pub player_id: PlayerId,
+    pub inputs: PlayerInputs,
+}
+
+#[derive(Debug)]
+pub struct PlayerInputState {
+    /// Tick of the last inputs applied for this player
+    pub last_tick: u64,
+    /// Time the player is still allowed to move for, refilled in real time
+    time_budget: f32,
+}
+
+impl Default for PlayerInputState {
+    /// Joining players start with a full budget, so that their first inputs are not cut
+    fn default() -> Self {
+        Self {
+            last_tick: 0,
+            time_budget: MAX_TIME_BUDGET,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PlayerInputStates {
+    pub players: HashMap<PlayerId, PlayerInputState>,
+}
+
+/// Moves the players from their inputs, the resulting positions are broadcast to every client
+pub fn handle_player_inputs(
+    time: Res<Time>,
+    mut lobby: ResMut<ServerLobby>,
+    mut world_map: ResMut<ServerWorldMap>,
+    mut input_states: ResMut<PlayerInputStates>,
+    mut events: EventReader<PlayerInputsEvent>,
+) {
+    // Players can only move for the time which really elapsed, preventing speed hacks
+    for state in input_states.players.values_mut() {
+        state.time_budget = (state.time_budget + time.delta_seconds()).min(MAX_TIME_BUDGET);
+    }
+
+    for event in events.read() {
+        let Some(player) = lobby.players.get_mut(&event.player_id) else {
+            continue;
+        };
+        let state = input_states.players.entry(event.player_id).or_default();
+
+        // Ignore duplicated or outdated inputs
+        if event.inputs.tick <= state.last_tick {
+            continue;
+        }
+
+        if event.inputs.direction.is_finite() {
+            player.orientation = PlayerOrientation::from_direction(event.inputs.direction);
+        }
+
+        let Some(mut position) = world_map.player_positions.get(&event.player_id).copied() else {
+            continue;
+        };
+
+        // Wait for the terrain around the player to be generated
+        let chunk_pos = global_block_to_chunk_pos(&position.floor().as_ivec3());
+        if !world_map.map.contains_key(&chunk_pos) {
+            continue;
+        }
+
+        let delta = if event.inputs.delta.is_finite() {
+            event.inputs.delta.clamp(0.0, MAX_INPUT_DELTA)
+        } else {
+            0.0
+        };
+        // Without budget left, the inputs are not applied : the client keeps predicting them
+        // until inputs of a later tick are acknowledged
+        if delta > 0.0 && state.time_budget <= 0.0 {
+            continue;
+        }
+        let delta = delta.min(state.time_budget);
+        state.time_budget -= delta;
+
+        simulate_player_movement(player, &mut position, &*world_map, &event.inputs, delta);
+        world_map.player_positions.insert(event.player_id, position);
+        // Only the simulated inputs are acknowledged to the client, with its movement state
+        state.last_tick = event.inputs.tick;
     }
 }
@@ -0,0 +1,33 @@
+use crate::world::save::{save_world_system, SaveRequestEvent};
+use bevy::prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+/// Set by the handler of SIGINT and SIGTERM, the server saves then stops on the next tick
+#[derive(Resource)]
+pub struct ShutdownSignal(Arc<AtomicBool>);
+
+/// Only used by dedicated servers, a solo world is saved when the game leaves it
+pub fn setup_shutdown_signal(app: &mut App) {
+    let signal = Arc::new(AtomicBool::new(false));
+    let handler_signal = signal.clone();
+    if let Err(e) = ctrlc::set_handler(move || handler_signal.store(true, Ordering::SeqCst)) {
+        error!("Failed to set the shutdown signal handler: {}", e);
+        return;
+    }
+
+    app.insert_resource(ShutdownSignal(signal));
+    app.add_systems(Update, handle_shutdown_signal.before(save_world_system));
+}
+
+fn handle_shutdown_signal(
+    signal: Res<ShutdownSignal>,
+    mut ev_save: EventWriter<SaveRequestEvent>,
+    mut ev_app_exit: EventWriter<AppExit>,
+) {
+    if signal.0.swap(false, Ordering::SeqCst) {
+        info!("Shutdown signal received, saving the world before stopping...");
+        ev_save.send(SaveRequestEvent);
+        ev_app_exit.send(AppExit::Success);
+    }
+}
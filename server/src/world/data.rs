@@ -1,14 +1,24 @@
-use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use shared::world::ServerChunk;
-use std::collections::HashMap;
+use shared::world::{get_game_folder, WorldSeed};
+use shared::GameFolderPaths;
+use std::path::PathBuf;
 
 pub const SAVE_PATH: &str = "saves/";
+pub const LEVEL_FILE_NAME: &str = "level.ron";
+pub const REGION_FOLDER_NAME: &str = "region";
+pub const PENDING_BLOCKS_FILE_NAME: &str = "pending_blocks.bin";
 
+/// World metadata, stored in `saves/<world>/level.ron`\
+/// Chunks are stored separately in region files, see `RegionStorage`
 #[derive(Serialize, Deserialize)]
-pub struct Save {
-    pub map: HashMap<IVec3, ServerChunk>,
-    // pub player_pos: Vec3,
-    // pub inventory: HashMap<RegistryId, Item>,
-    // pub id_to_item: HashMap<RegistryId, String>,
+pub struct LevelData {
+    pub seed: WorldSeed,
+    pub time: u64,
+}
+
+/// Returns the folder holding every file of the given world
+pub fn get_world_folder(game_folder_path: &GameFolderPaths, world_name: &str) -> PathBuf {
+    get_game_folder(Some(game_folder_path))
+        .join(SAVE_PATH)
+        .join(world_name)
 }
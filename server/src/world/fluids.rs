@@ -0,0 +1,302 @@
+use bevy::prelude::*;
+use shared::world::{
+    global_block_to_chunk_pos, BlockData, BlockDirection, BlockId, BlockTransparency,
+    ServerWorldMap, LIQUID_FALLING_LEVEL, LIQUID_MAX_FLOW_LEVEL, LIQUID_SOURCE_LEVEL, SIX_OFFSETS,
+};
+use std::collections::{BTreeMap, HashSet};
+
+use crate::init::TickCounter;
+use crate::world::region::RegionStorage;
+
+/// Maximum number of liquid blocks updated during a single tick, the others wait for the next ones
+const MAX_UPDATES_PER_TICK: usize = 1024;
+
+const HORIZONTAL_OFFSETS: [IVec3; 4] = [
+    IVec3::new(1, 0, 0),
+    IVec3::new(-1, 0, 0),
+    IVec3::new(0, 0, 1),
+    IVec3::new(0, 0, -1),
+];
+
+/// Positions of the blocks whose liquid must be updated, indexed by tick
+#[derive(Resource, Default)]
+pub struct FluidTicks {
+    scheduled: BTreeMap<u64, HashSet<IVec3>>,
+}
+
+impl FluidTicks {
+    pub fn schedule(&mut self, position: IVec3, tick: u64) {
+        self.scheduled.entry(tick).or_default().insert(position);
+    }
+
+    /// Schedules an update of this block and of its six neighbours, after a block change
+    pub fn schedule_around(&mut self, position: &IVec3, tick: u64) {
+        self.schedule(*position, tick);
+        for offset in SIX_OFFSETS.iter() {
+            self.schedule(*position + *offset, tick);
+        }
+    }
+
+    /// Removes and returns the updates due at this tick, up to `max` positions
+    fn take_due(&mut self, tick: u64, max: usize) -> Vec<IVec3> {
+        let mut due: Vec<IVec3> = Vec::new();
+        while due.len() < max {
+            let Some(mut entry) = self.scheduled.first_entry() else {
+                break;
+            };
+            if *entry.key() > tick {
+                break;
+            }
+
+            let positions = entry.get_mut();
+            let remaining = max - due.len();
+            if positions.len() <= remaining {
+                due.extend(entry.remove());
+            } else {
+                let taken: Vec<IVec3> = positions.iter().take(remaining).copied().collect();
+                for position in taken.iter() {
+                    positions.remove(position);
+                }
+                due.extend(taken);
+            }
+        }
+        due
+    }
+}
+
+/// Number of ticks between two spreading steps
+fn flow_delay(liquid: BlockId) -> u64 {
+    match liquid {
+        BlockId::Lava => 30,
+        _ => 5,
+    }
+}
+
+/// Level lost at each block of horizontal flow
+fn flow_step(liquid: BlockId) -> u8 {
+    match liquid {
+        BlockId::Lava => 2,
+        _ => 1,
+    }
+}
+
+/// Liquids can only flow in loaded chunks
+fn is_loaded(world_map: &ServerWorldMap, position: &IVec3) -> bool {
+    world_map
+        .map
+        .contains_key(&global_block_to_chunk_pos(position))
+}
+
+fn is_same_liquid(block: Option<&BlockData>, liquid: BlockId) -> bool {
+    block.is_some_and(|block| block.id == liquid)
+}
+
+/// Air and decorations (flowers...) are replaced by flowing liquids
+fn is_replaceable(block: Option<&BlockData>) -> bool {
+    match block {
+        None => true,
+        Some(block) => block.id.get_visibility() == BlockTransparency::Decoration,
+    }
+}
+
+/// Level used by the neighbours of a liquid block to compute their own level
+fn spreading_level(block: &BlockData) -> u8 {
+    if block.level == LIQUID_FALLING_LEVEL {
+        LIQUID_SOURCE_LEVEL
+    } else {
+        block.level
+    }
+}
+
+struct FluidUpdater<'a> {
+    world_map: &'a mut ServerWorldMap,
+    region_storage: &'a mut RegionStorage,
+    fluid_ticks: &'a mut FluidTicks,
+    tick: u64,
+}
+
+impl FluidUpdater<'_> {
+    fn get(&self, position: &IVec3) -> Option<&BlockData> {
+        self.world_map.get_block_by_coordinates(position)
+    }
+
+    fn set(&mut self, position: &IVec3, block: Option<BlockData>, delay: u64) {
+        match block {
+            Some(block) => self.world_map.set_block(position, block),
+            None => {
+                self.world_map.remove_block_by_coordinates(position);
+            }
+        }
+        self.region_storage
+            .mark_dirty(&global_block_to_chunk_pos(position));
+        self.fluid_ticks
+            .schedule_around(position, self.tick + delay);
+    }
+
+    /// Whether the block at this position is supported from below, so a liquid on it spreads sideways
+    fn can_spread_sideways(&self, position: &IVec3, liquid: BlockId) -> bool {
+        let below = *position - IVec3::Y;
+        let block_below = self.get(&below);
+        is_loaded(self.world_map, &below)
+            && !is_replaceable(block_below)
+            && !(is_same_liquid(block_below, liquid)
+                && block_below.unwrap().level != LIQUID_SOURCE_LEVEL)
+    }
+
+    /// Computes the level a flowing liquid block should have from its neighbours,
+    /// `None` if it is no longer fed and must disappear
+    fn expected_level(&self, position: &IVec3, liquid: BlockId) -> Option<u8> {
+        if is_same_liquid(self.get(&(*position + IVec3::Y)), liquid) {
+            return Some(LIQUID_FALLING_LEVEL);
+        }
+
+        let mut sources_count = 0;
+        let mut level: Option<u8> = None;
+        for offset in HORIZONTAL_OFFSETS.iter() {
+            let neighbor_pos = *position + *offset;
+            let Some(neighbor) = self.get(&neighbor_pos).copied() else {
+                continue;
+            };
+            if neighbor.id != liquid || !self.can_spread_sideways(&neighbor_pos, liquid) {
+                continue;
+            }
+            if neighbor.level == LIQUID_SOURCE_LEVEL {
+                sources_count += 1;
+            }
+            let candidate = spreading_level(&neighbor) + flow_step(liquid);
+            if candidate <= LIQUID_MAX_FLOW_LEVEL {
+                level = Some(level.map_or(candidate, |level| level.min(candidate)));
+            }
+        }
+
+        // Water between two sources becomes a source itself
+        if liquid == BlockId::Water && sources_count >= 2 {
+            let below = self.get(&(*position - IVec3::Y));
+            if below
+                .is_some_and(|block| !block.id.is_liquid() || block.level == LIQUID_SOURCE_LEVEL)
+            {
+                return Some(LIQUID_SOURCE_LEVEL);
+            }
+        }
+
+        level
+    }
+
+    /// Makes the liquid flow into a neighbouring block, returns true if it was modified
+    fn flow_into(&mut self, target: &IVec3, liquid: BlockId, level: u8) -> bool {
+        if !is_loaded(self.world_map, target) {
+            return false;
+        }
+        let delay = flow_delay(liquid);
+
+        match self.get(target).copied() {
+            Some(block) if block.id.is_liquid() && block.id != liquid => {
+                // Water and lava harden when they meet
+                let result = if liquid == BlockId::Lava || block.level == LIQUID_SOURCE_LEVEL {
+                    BlockId::Stone
+                } else {
+                    BlockId::Cobblestone
+                };
+                self.set(
+                    target,
+                    Some(BlockData::new(result, false, BlockDirection::Front)),
+                    delay,
+                );
+                true
+            }
+            Some(block) if block.id == liquid => {
+                // Only raise the level of flowing liquid
+                if block.level != LIQUID_SOURCE_LEVEL
+                    && block.level != LIQUID_FALLING_LEVEL
+                    && block.level > level
+                {
+                    self.set(target, Some(BlockData::liquid(liquid, level)), delay);
+                    return true;
+                }
+                false
+            }
+            block if is_replaceable(block.as_ref()) => {
+                self.set(target, Some(BlockData::liquid(liquid, level)), delay);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn update(&mut self, position: &IVec3) {
+        if !is_loaded(self.world_map, position) {
+            return;
+        }
+        let Some(mut block) = self.get(position).copied() else {
+            return;
+        };
+        if !block.id.is_liquid() {
+            return;
+        }
+        let liquid = block.id;
+        let delay = flow_delay(liquid);
+
+        // Flowing liquid follows its neighbours
+        if block.level != LIQUID_SOURCE_LEVEL {
+            match self.expected_level(position, liquid) {
+                None => {
+                    self.set(position, None, delay);
+                    return;
+                }
+                Some(level) if level != block.level => {
+                    block.level = level;
+                    self.set(position, Some(block), delay);
+                    return;
+                }
+                _ => {}
+            }
+        }
+
+        // Fall first, then spread on the sides when supported
+        let below = *position - IVec3::Y;
+        if is_replaceable(self.get(&below))
+            || self
+                .get(&below)
+                .is_some_and(|b| b.id != liquid && b.id.is_liquid())
+        {
+            self.flow_into(&below, liquid, LIQUID_FALLING_LEVEL);
+            return;
+        }
+        if !self.can_spread_sideways(position, liquid) {
+            return;
+        }
+
+        let level = spreading_level(&block) + flow_step(liquid);
+        if level > LIQUID_MAX_FLOW_LEVEL {
+            return;
+        }
+        for offset in HORIZONTAL_OFFSETS.iter() {
+            self.flow_into(&(*position + *offset), liquid, level);
+        }
+    }
+}
+
+/// Spreads water and lava, in the blocks scheduled by `FluidTicks`\
+/// Modified blocks are sent to the clients through `block_changes`
+pub fn fluid_tick_system(
+    mut world_map: ResMut<ServerWorldMap>,
+    mut region_storage: ResMut<RegionStorage>,
+    mut fluid_ticks: ResMut<FluidTicks>,
+    ticker: Res<TickCounter>,
+) {
+    let due = fluid_ticks.take_due(ticker.tick, MAX_UPDATES_PER_TICK);
+    if due.is_empty() {
+        return;
+    }
+
+    let mut updater = FluidUpdater {
+        world_map: &mut world_map,
+        region_storage: &mut region_storage,
+        fluid_ticks: &mut fluid_ticks,
+        tick: ticker.tick,
+    };
+    for position in due.iter() {
+        updater.update(position);
+    }
+    trace!("Fluid tick : {} blocks updated", due.len());
+}
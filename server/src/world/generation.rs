@@ -1,16 +1,292 @@
 use bevy::prelude::*;
+use bincode::Options;
 use noise::{NoiseFn, Perlin};
+use serde::{Deserialize, Serialize};
+use shared::messages::BlockChange;
 use shared::{world::*, CHUNK_SIZE};
+use std::collections::hash_map::Entry;
 use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
 
-fn generate_tree(chunk: &mut ServerChunk, x: i32, y: i32, z: i32, trunk: BlockId, leaves: BlockId) {
+use crate::world::data::PENDING_BLOCKS_FILE_NAME;
+use crate::world::region::RegionStorage;
+use crate::world::save::write_atomic;
+
+/// Deterministic random number generator used for decorations\
+/// Seeded from the world seed and a block position, so a seed always produces the same world
+/// and a chunk generated twice is identical
+pub struct GenerationRng(u64);
+
+impl GenerationRng {
+    pub fn new(seed: u32, chunk_pos: &IVec3, local_pos: &IVec3) -> Self {
+        let mut rng = GenerationRng(seed as u64);
+        for value in [
+            chunk_pos.x,
+            chunk_pos.y,
+            chunk_pos.z,
+            local_pos.x,
+            local_pos.y,
+            local_pos.z,
+        ] {
+            rng.0 ^= value as u32 as u64;
+            rng.next_u64();
+        }
+        rng
+    }
+
+    /// SplitMix64 step
+    pub fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// Random value between 0 (inclusive) and 1 (exclusive)
+    pub fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    /// Random value in `min..max`
+    pub fn next_range(&mut self, min: i32, max: i32) -> i32 {
+        min + (self.next_u64() % (max - min) as u64) as i32
+    }
+
+    /// Derives an independent generator, for features sharing the same position
+    pub fn with_salt(mut self, salt: u64) -> Self {
+        self.0 ^= salt;
+        self.next_u64();
+        self
+    }
+}
+
+/// Distribution of an ore in the world
+struct OreConfig {
+    block: BlockId,
+    /// Global height range of the veins (inclusive)
+    min_y: i32,
+    max_y: i32,
+    /// Number of blocks a vein tries to place
+    vein_size: u32,
+    /// Average number of veins per chunk inside the height range
+    veins_per_chunk: f32,
+}
+
+const ORES: [OreConfig; 4] = [
+    OreConfig {
+        block: BlockId::CoalOre,
+        min_y: 5,
+        max_y: 90,
+        vein_size: 12,
+        veins_per_chunk: 2.5,
+    },
+    OreConfig {
+        block: BlockId::IronOre,
+        min_y: 5,
+        max_y: 64,
+        vein_size: 8,
+        veins_per_chunk: 1.5,
+    },
+    OreConfig {
+        block: BlockId::GoldOre,
+        min_y: 5,
+        max_y: 32,
+        vein_size: 6,
+        veins_per_chunk: 0.5,
+    },
+    OreConfig {
+        block: BlockId::DiamondOre,
+        min_y: 1,
+        max_y: 16,
+        vein_size: 5,
+        veins_per_chunk: 0.3,
+    },
+];
+
+/// Replaces some stone of the chunk with ore veins\
+/// A vein is a random walk inside the chunk, only replacing stone
+fn generate_ores(chunk: &mut ServerChunk, chunk_pos: &IVec3, seed: u32) {
+    let chunk_min_y = chunk_pos.y * CHUNK_SIZE;
+    let chunk_max_y = chunk_min_y + CHUNK_SIZE - 1;
+
+    for (index, ore) in ORES.iter().enumerate() {
+        let min_y = ore.min_y.max(chunk_min_y);
+        let max_y = ore.max_y.min(chunk_max_y);
+        if min_y > max_y {
+            continue;
+        }
+
+        let mut rng = GenerationRng::new(seed, chunk_pos, &IVec3::ZERO).with_salt(index as u64);
+
+        let mut veins_count = ore.veins_per_chunk as u32;
+        if rng.next_f32() < ore.veins_per_chunk.fract() {
+            veins_count += 1;
+        }
+
+        for _ in 0..veins_count {
+            let mut pos = IVec3::new(
+                rng.next_range(0, CHUNK_SIZE),
+                rng.next_range(min_y, max_y + 1) - chunk_min_y,
+                rng.next_range(0, CHUNK_SIZE),
+            );
+
+            for _ in 0..ore.vein_size {
+                if chunk.blocks.get(&pos).map(|block| block.id) == Some(BlockId::Stone) {
+                    chunk
+                        .blocks
+                        .insert(pos, BlockData::new(ore.block, false, BlockDirection::Front));
+                }
+
+                let step = SIX_OFFSETS[rng.next_range(0, SIX_OFFSETS.len() as i32) as usize];
+                pos = (pos + step).clamp(IVec3::ZERO, IVec3::splat(CHUNK_SIZE - 1));
+            }
+        }
+    }
+}
+
+/// Blocks placed by features (trees, cacti, flowers), positioned relative to the chunk being generated\
+/// Positions can be outside of the chunk : those blocks are written in the neighbour chunks
+type StructureBlocks = Vec<(IVec3, BlockData)>;
+
+/// Structure blocks waiting for their chunk to be generated or loaded, indexed by chunk position
+///
+/// They are saved with the regions, in `pending_blocks.bin` in the world folder,
+/// so that structures crossing the border of a chunk not generated yet stay complete.
+#[derive(Resource, Default, Serialize, Deserialize)]
+pub struct PendingBlocks {
+    chunks: HashMap<IVec3, Vec<(IVec3, BlockData)>>,
+}
+
+impl PendingBlocks {
+    /// Reads the blocks left pending by the previous runs of the server, none if the file does not exist
+    pub fn load(world_folder: &Path) -> Self {
+        let path = world_folder.join(PENDING_BLOCKS_FILE_NAME);
+        if !path.exists() {
+            return Self::default();
+        }
+
+        match fs::read(&path)
+            .map_err(|e| e.to_string())
+            .and_then(|bytes| {
+                bincode::options()
+                    .deserialize::<Self>(&bytes)
+                    .map_err(|e| e.to_string())
+            }) {
+            Ok(pending) => {
+                info!("Pending blocks loaded for {} chunks", pending.chunks.len());
+                pending
+            }
+            Err(e) => {
+                error!("Failed to load pending blocks from {:?}: {}", path, e);
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&self, world_folder: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        let bytes = bincode::options().serialize(self)?;
+        write_atomic(&world_folder.join(PENDING_BLOCKS_FILE_NAME), &bytes)?;
+        Ok(())
+    }
+
+    fn push(&mut self, chunk_pos: IVec3, local_pos: IVec3, block: BlockData) {
+        self.chunks
+            .entry(chunk_pos)
+            .or_default()
+            .push((local_pos, block));
+    }
+}
+
+/// Writes a block of a structure, without replacing the terrain
+fn place_structure_block(chunk: &mut ServerChunk, local_pos: IVec3, block: BlockData) -> bool {
+    if chunk.blocks.contains_key(&local_pos) {
+        return false;
+    }
+    chunk.blocks.insert(local_pos, block);
+    true
+}
+
+/// Places the structure blocks in the chunk being generated, the others are kept for its neighbours
+fn place_structures(
+    chunk: &mut ServerChunk,
+    chunk_pos: &IVec3,
+    structures: StructureBlocks,
+    pending: &mut PendingBlocks,
+) {
+    for (pos, block) in structures {
+        let target_chunk = *chunk_pos + pos.div_euclid(IVec3::splat(CHUNK_SIZE));
+        let local_pos = pos.rem_euclid(IVec3::splat(CHUNK_SIZE));
+
+        if target_chunk == *chunk_pos {
+            place_structure_block(chunk, local_pos, block);
+        } else {
+            pending.push(target_chunk, local_pos, block);
+        }
+    }
+
+    // Apply the blocks left by the structures of the chunks generated before
+    if let Some(blocks) = pending.chunks.remove(chunk_pos) {
+        for (local_pos, block) in blocks {
+            place_structure_block(chunk, local_pos, block);
+        }
+    }
+}
+
+/// Writes the pending structure blocks into the chunks which are already loaded or saved\
+/// Modified chunks are sent again to the clients
+pub fn apply_pending_blocks(
+    world_map: &mut ServerWorldMap,
+    region_storage: &mut RegionStorage,
+    pending: &mut PendingBlocks,
+) {
+    let targets: Vec<IVec3> = pending.chunks.keys().copied().collect();
+    for chunk_pos in targets {
+        if let Entry::Vacant(entry) = world_map.map.entry(chunk_pos) {
+            match region_storage.load_chunk(&chunk_pos) {
+                Some(chunk) => {
+                    entry.insert(chunk);
+                }
+                // Not generated yet, the blocks will be placed by generate_chunk
+                None => continue,
+            }
+        }
+
+        let chunk = world_map.map.get_mut(&chunk_pos).unwrap();
+        let mut modified = false;
+        for (local_pos, block) in pending.chunks.remove(&chunk_pos).unwrap_or_default() {
+            if place_structure_block(chunk, local_pos, block) {
+                modified = true;
+                world_map.block_changes.push(BlockChange {
+                    position: to_global_pos(&chunk_pos, &local_pos),
+                    block: Some(block),
+                });
+            }
+        }
+
+        if modified {
+            region_storage.mark_dirty(&chunk_pos);
+        }
+    }
+}
+
+fn generate_tree(
+    structures: &mut StructureBlocks,
+    rng: &mut GenerationRng,
+    x: i32,
+    y: i32,
+    z: i32,
+    trunk: BlockId,
+    leaves: BlockId,
+) {
     // create trunk
-    let trunk_height = 3 + rand::random::<u8>() % 3; // random height between 3 and 5
+    let trunk_height = 3 + rng.next_u64() % 3; // random height between 3 and 5
     for dy in 0..trunk_height {
-        chunk.map.insert(
+        structures.push((
             IVec3::new(x, y + dy as i32, z),
             BlockData::new(trunk, false, BlockDirection::Front),
-        );
+        ));
     }
 
     // place the leaves
@@ -18,27 +294,34 @@ fn generate_tree(chunk: &mut ServerChunk, x: i32, y: i32, z: i32, trunk: BlockId
     for offset_x in -1..=1 {
         for offset_z in -1..=1 {
             if (offset_x != 0 || offset_z != 0) && (offset_x == 0 || offset_z == 0) {
-                chunk.map.insert(
+                structures.push((
                     IVec3::new(x + offset_x, leaf_start_y, z + offset_z),
                     BlockData::new(leaves, false, BlockDirection::Front),
-                );
+                ));
             }
         }
     }
     // add one leaf block at the top of the trunk
-    chunk.map.insert(
+    structures.push((
         IVec3::new(x, leaf_start_y + 1, z),
         BlockData::new(leaves, false, BlockDirection::Front),
-    );
+    ));
 }
 
-fn generate_cactus(chunk: &mut ServerChunk, x: i32, y: i32, z: i32, cactus: BlockId) {
-    let cactus_height = 1 + rand::random::<u8>() % 3;
+fn generate_cactus(
+    structures: &mut StructureBlocks,
+    rng: &mut GenerationRng,
+    x: i32,
+    y: i32,
+    z: i32,
+    cactus: BlockId,
+) {
+    let cactus_height = 1 + rng.next_u64() % 3;
     for dy in 0..cactus_height {
-        chunk.map.insert(
+        structures.push((
             IVec3::new(x, y + dy as i32, z),
             BlockData::new(cactus, false, BlockDirection::Front),
-        );
+        ));
     }
 }
 
@@ -66,7 +349,15 @@ pub fn determine_biome(temperature: f64, humidity: f64) -> BiomeType {
     }
 }
 
-fn interpolated_height(
+/// Shape of a terrain column, blended between the neighbouring biomes
+struct TerrainColumn {
+    height: i32,
+    overhang_amplitude: f64,
+    cave_threshold: f64,
+    ravine_depth: f64,
+}
+
+fn interpolated_column(
     x: i32,
     z: i32,
     biome_scale: f64,
@@ -74,7 +365,7 @@ fn interpolated_height(
     temp_perlin: &Perlin,
     humidity_perlin: &Perlin,
     scale: f64,
-) -> i32 {
+) -> TerrainColumn {
     // get the properties of the main biome at (x, z)
     let temperature =
         (temp_perlin.get([x as f64 * biome_scale, z as f64 * biome_scale]) + 1.0) / 2.0;
@@ -86,6 +377,9 @@ fn interpolated_height(
     // initialize weighted values
     let mut weighted_base_height = biome.base_height as f64;
     let mut weighted_variation = biome.height_variation as f64;
+    let mut weighted_overhang = biome.overhang_amplitude;
+    let mut weighted_cave_threshold = biome.cave_threshold;
+    let mut weighted_ravine_depth = biome.ravine_depth as f64;
     let mut total_weight = 1.0;
 
     // loop through neighboring blocks to get influences
@@ -121,6 +415,9 @@ fn interpolated_height(
             // update weighted values
             weighted_base_height += neighbor_biome.base_height as f64 * weight;
             weighted_variation += neighbor_biome.height_variation as f64 * weight;
+            weighted_overhang += neighbor_biome.overhang_amplitude * weight;
+            weighted_cave_threshold += neighbor_biome.cave_threshold * weight;
+            weighted_ravine_depth += neighbor_biome.ravine_depth as f64 * weight;
             total_weight += weight;
         }
     }
@@ -133,13 +430,124 @@ fn interpolated_height(
     let terrain_noise = perlin.get([x as f64 * scale, z as f64 * scale]);
     let interpolated_height = weighted_base_height + (weighted_variation * terrain_noise);
 
-    interpolated_height.round() as i32
+    TerrainColumn {
+        height: interpolated_height.round() as i32,
+        overhang_amplitude: weighted_overhang / total_weight,
+        cave_threshold: weighted_cave_threshold / total_weight,
+        ravine_depth: weighted_ravine_depth / total_weight,
+    }
+}
+
+/// Number of sub-surface blocks (dirt, sand...) below the surface block
+const SUB_SURFACE_DEPTH: i32 = 4;
+/// Height of the water surface : terrain below is flooded
+pub const SEA_LEVEL: i32 = 62;
+/// Caves and ravines are filled with lava up to this height
+const LAVA_LEVEL: i32 = 10;
+/// Half width of rivers, in river noise units
+const RIVER_WIDTH: f64 = 0.04;
+/// Rivers lower the terrain up to this distance from their center, making the banks
+const RIVER_BANK_WIDTH: f64 = 0.1;
+/// Half width of ravines, in ravine noise units
+const RAVINE_WIDTH: f64 = 0.03;
+
+/// 3D noises carving the terrain, all derived from the world seed
+struct TerrainNoises {
+    density: Perlin,
+    cave_a: Perlin,
+    cave_b: Perlin,
+    ravine: Perlin,
+    continent: Perlin,
+    lake: Perlin,
+    river: Perlin,
+}
+
+impl TerrainNoises {
+    fn new(seed: u32) -> Self {
+        Self {
+            density: Perlin::new(seed.wrapping_add(3)),
+            cave_a: Perlin::new(seed.wrapping_add(4)),
+            cave_b: Perlin::new(seed.wrapping_add(5)),
+            ravine: Perlin::new(seed.wrapping_add(6)),
+            continent: Perlin::new(seed.wrapping_add(7)),
+            lake: Perlin::new(seed.wrapping_add(8)),
+            river: Perlin::new(seed.wrapping_add(9)),
+        }
+    }
+
+    /// Lowers the terrain into oceans, lakes and river beds, which get flooded up to `SEA_LEVEL`
+    fn apply_water_bodies(&self, x: i32, z: i32, column: &mut TerrainColumn) {
+        let mut height = column.height as f64;
+
+        // Oceans : large areas of low continentalness
+        let continent = self.continent.get([x as f64 * 0.004, z as f64 * 0.004]);
+        if continent < -0.25 {
+            height -= ((-0.25 - continent) / 0.3).min(1.0) * 24.0;
+        }
+
+        // Lakes : smaller basins
+        let lake = self.lake.get([x as f64 * 0.03, z as f64 * 0.03]);
+        if lake > 0.55 {
+            height -= (lake - 0.55) * 40.0;
+        }
+
+        // Rivers : follow the zero line of their own noise
+        let river = self.river.get([x as f64 * 0.006, z as f64 * 0.006]).abs();
+        if river < RIVER_WIDTH {
+            let bed = (SEA_LEVEL - 1) as f64 - 3.0 * (1.0 - river / RIVER_WIDTH);
+            height = height.min(bed);
+        } else if river < RIVER_BANK_WIDTH && height > SEA_LEVEL as f64 {
+            let t = (river - RIVER_WIDTH) / (RIVER_BANK_WIDTH - RIVER_WIDTH);
+            height = SEA_LEVEL as f64 + (height - SEA_LEVEL as f64) * t;
+        }
+
+        column.height = height.round() as i32;
+    }
+
+    /// Whether the terrain is solid before carving : the heightmap is displaced by 3D noise,
+    /// which creates overhangs where the amplitude is high enough
+    fn is_terrain(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> bool {
+        let distance = (column.height - y) as f64;
+        if distance.abs() > column.overhang_amplitude {
+            return distance >= 0.0;
+        }
+        let noise = self
+            .density
+            .get([x as f64 * 0.05, y as f64 * 0.08, z as f64 * 0.05]);
+        distance + column.overhang_amplitude * noise >= 0.0
+    }
+
+    /// Caves are tunnels along the intersection of two noises, kept below the sub-surface layer
+    fn is_cave(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> bool {
+        if y >= column.height - SUB_SURFACE_DEPTH {
+            return false;
+        }
+        let point = [x as f64 * 0.04, y as f64 * 0.06, z as f64 * 0.04];
+        let a = self.cave_a.get(point);
+        let b = self.cave_b.get(point);
+        a * a + b * b < column.cave_threshold
+    }
+
+    /// Ravines are narrow cuts from the surface, deepest at their center
+    fn is_ravine(&self, x: i32, y: i32, z: i32, column: &TerrainColumn) -> bool {
+        // No ravines under water, it would be left floating above them
+        if column.ravine_depth <= 0.0 || column.height <= SEA_LEVEL + 2 {
+            return false;
+        }
+        let noise = self.ravine.get([x as f64 * 0.008, z as f64 * 0.008]).abs();
+        if noise >= RAVINE_WIDTH {
+            return false;
+        }
+        let depth = column.ravine_depth * (1.0 - noise / RAVINE_WIDTH);
+        (y as f64) > column.height as f64 - depth
+    }
 }
 
-pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
+pub fn generate_chunk(chunk_pos: IVec3, seed: u32, pending: &mut PendingBlocks) -> ServerChunk {
     let perlin = Perlin::new(seed);
-    let temp_perlin = Perlin::new(seed + 1);
-    let humidity_perlin = Perlin::new(seed + 2);
+    let temp_perlin = Perlin::new(seed.wrapping_add(1));
+    let humidity_perlin = Perlin::new(seed.wrapping_add(2));
+    let noises = TerrainNoises::new(seed);
 
     let scale = 0.1;
     let biome_scale = 0.02;
@@ -148,12 +556,13 @@ pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
     let cz = chunk_pos.z;
 
     let mut chunk = ServerChunk {
-        map: HashMap::new(),
+        blocks: ChunkStorage::default(),
         ts: std::time::SystemTime::now()
             .duration_since(std::time::UNIX_EPOCH)
             .unwrap()
             .as_millis() as u64,
     };
+    let mut structures: StructureBlocks = Vec::new();
 
     for dx in 0..CHUNK_SIZE {
         for dz in 0..CHUNK_SIZE {
@@ -170,8 +579,8 @@ pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
             let biome_type = determine_biome(temperature, humidity);
             let biome = get_biome_data(biome_type);
 
-            // get terrain height
-            let terrain_height = interpolated_height(
+            // get terrain shape
+            let mut column = interpolated_column(
                 x,
                 z,
                 biome_scale,
@@ -180,110 +589,167 @@ pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
                 &humidity_perlin,
                 scale,
             );
+            noises.apply_water_bodies(x, z, &mut column);
 
-            // generate blocs
-            for dy in 0..CHUNK_SIZE {
+            // Skip columns entirely above the terrain and the water
+            let column_top =
+                (column.height + column.overhang_amplitude.ceil() as i32).max(SEA_LEVEL);
+            if CHUNK_SIZE * cy > column_top {
+                continue;
+            }
+
+            // Go down the column, starting a few blocks above the chunk to know how deep
+            // each block is below the surface
+            // When the scan starts underground, its first block is not the surface
+            let scan_top = CHUNK_SIZE * cy + CHUNK_SIZE + SUB_SURFACE_DEPTH;
+            let starts_underground = noises.is_terrain(x, scan_top, z, &column);
+            let mut depth = if starts_underground { 1 } else { 0 };
+            let mut above_is_air = !starts_underground;
+            for dy in (0..CHUNK_SIZE + SUB_SURFACE_DEPTH).rev() {
                 let y = CHUNK_SIZE * cy + dy;
 
-                if y > terrain_height {
-                    break;
+                if !noises.is_terrain(x, y, z, &column) && y != 0 {
+                    depth = 0;
+                    above_is_air = y > SEA_LEVEL;
+
+                    // Flood everything under the sea level, freezing the surface in cold biomes
+                    if y <= SEA_LEVEL && dy < CHUNK_SIZE {
+                        let water = if y == SEA_LEVEL && biome_type == BiomeType::IcePlain {
+                            BlockId::Ice
+                        } else {
+                            BlockId::Water
+                        };
+                        chunk.blocks.insert(
+                            IVec3::new(dx, dy, dz),
+                            BlockData::new(water, false, BlockDirection::Front),
+                        );
+                    }
+                    continue;
                 }
 
+                let is_surface = depth == 0;
                 let block = if y == 0 {
                     BlockId::Bedrock
-                } else if y < terrain_height - 4 {
-                    BlockId::Stone
-                } else if y < terrain_height {
-                    biome.sub_surface_block
-                } else if y == terrain_height {
+                } else if depth <= SUB_SURFACE_DEPTH && column.height <= SEA_LEVEL + 1 {
+                    // Beaches and sea floor
+                    BlockId::Sand
+                } else if is_surface {
                     biome.surface_block
+                } else if depth <= SUB_SURFACE_DEPTH {
+                    biome.sub_surface_block
                 } else {
-                    panic!();
+                    BlockId::Stone
                 };
+                depth += 1;
+
+                // Bedrock is never carved
+                if y != 0
+                    && (noises.is_cave(x, y, z, &column) || noises.is_ravine(x, y, z, &column))
+                {
+                    if y <= LAVA_LEVEL {
+                        above_is_air = false;
+                        if dy < CHUNK_SIZE {
+                            chunk.blocks.insert(
+                                IVec3::new(dx, dy, dz),
+                                BlockData::liquid(BlockId::Lava, LIQUID_SOURCE_LEVEL),
+                            );
+                        }
+                    } else {
+                        above_is_air = true;
+                    }
+                    continue;
+                }
+
+                let has_air_above = above_is_air;
+                above_is_air = false;
+
+                // Blocks above the chunk are only computed for the depth
+                if dy >= CHUNK_SIZE {
+                    continue;
+                }
 
                 let block_pos = IVec3::new(dx, dy, dz);
 
-                chunk.map.insert(
+                chunk.blocks.insert(
                     block_pos,
                     BlockData::new(block, false, BlockDirection::Front),
                 );
 
                 // Add flora in some biomes
-                if y == terrain_height && terrain_height >= 1 {
+                if is_surface && has_air_above && block == biome.surface_block && y >= 1 {
+                    let mut rng = GenerationRng::new(seed, &chunk_pos, &block_pos);
                     match biome_type {
                         BiomeType::Forest => {
-                            let tree_chance = rand::random::<f32>();
+                            let tree_chance = rng.next_f32();
                             if tree_chance < 0.05 {
-                                // ensure the area above is clear before generating the tree
-                                let above_surface_pos = IVec3::new(dx, terrain_height + 1, dz);
-                                if !chunk.map.contains_key(&above_surface_pos) {
-                                    generate_tree(
-                                        &mut chunk,
-                                        dx,
-                                        dy + 1,
-                                        dz,
-                                        BlockId::OakLog,
-                                        BlockId::OakLeaves,
-                                    );
-                                }
+                                generate_tree(
+                                    &mut structures,
+                                    &mut rng,
+                                    dx,
+                                    dy + 1,
+                                    dz,
+                                    BlockId::OakLog,
+                                    BlockId::OakLeaves,
+                                );
                             } else if tree_chance < 0.075 {
-                                chunk.map.insert(
+                                structures.push((
                                     block_pos.with_y(block_pos.y + 1),
                                     BlockData::new(
                                         BlockId::Dandelion,
                                         false,
                                         BlockDirection::Front,
                                     ),
-                                );
+                                ));
                             } else if tree_chance < 0.1 {
-                                chunk.map.insert(
+                                structures.push((
                                     block_pos.with_y(block_pos.y + 1),
                                     BlockData::new(BlockId::Poppy, false, BlockDirection::Front),
-                                );
+                                ));
                             }
                         }
                         BiomeType::Desert => {
-                            let cactus_chance = rand::random::<f32>();
+                            let cactus_chance = rng.next_f32();
                             if cactus_chance < 0.02 {
-                                let above_surface_pos = IVec3::new(dx, terrain_height + 1, dz);
-                                if !chunk.map.contains_key(&above_surface_pos) {
-                                    generate_cactus(&mut chunk, dx, dy + 1, dz, BlockId::Cactus);
-                                }
+                                generate_cactus(
+                                    &mut structures,
+                                    &mut rng,
+                                    dx,
+                                    dy + 1,
+                                    dz,
+                                    BlockId::Cactus,
+                                );
                             }
                         }
                         BiomeType::IcePlain => {
-                            let tree_chance = rand::random::<f32>();
+                            let tree_chance = rng.next_f32();
                             if tree_chance < 0.05 {
-                                // ensure the area above is clear before generating the tree
-                                let above_surface_pos = IVec3::new(dx, terrain_height + 1, dz);
-                                if !chunk.map.contains_key(&above_surface_pos) {
-                                    generate_tree(
-                                        &mut chunk,
-                                        dx,
-                                        dy + 1,
-                                        dz,
-                                        BlockId::SpruceLog,
-                                        BlockId::SpruceLeaves,
-                                    );
-                                }
+                                generate_tree(
+                                    &mut structures,
+                                    &mut rng,
+                                    dx,
+                                    dy + 1,
+                                    dz,
+                                    BlockId::SpruceLog,
+                                    BlockId::SpruceLeaves,
+                                );
                             }
                         }
                         BiomeType::Plains => {
-                            let flower_chance = rand::random::<f32>();
+                            let flower_chance = rng.next_f32();
                             if flower_chance < 0.075 {
-                                chunk.map.insert(
+                                structures.push((
                                     block_pos.with_y(block_pos.y + 1),
                                     BlockData::new(
                                         BlockId::Dandelion,
                                         false,
                                         BlockDirection::Front,
                                     ),
-                                );
+                                ));
                             } else if flower_chance < 0.1 {
-                                chunk.map.insert(
+                                structures.push((
                                     block_pos.with_y(block_pos.y + 1),
                                     BlockData::new(BlockId::Poppy, false, BlockDirection::Front),
-                                );
+                                ));
                             }
                         }
                         _ => {}
@@ -292,5 +758,90 @@ pub fn generate_chunk(chunk_pos: IVec3, seed: u32) -> ServerChunk {
             }
         }
     }
+
+    generate_ores(&mut chunk, &chunk_pos, seed);
+
+    place_structures(&mut chunk, &chunk_pos, structures, pending);
     chunk
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// FNV-1a, stable across Rust versions unlike `DefaultHasher`
+    fn fnv1a(hash: &mut u64, bytes: &[u8]) {
+        for byte in bytes {
+            *hash ^= *byte as u64;
+            *hash = hash.wrapping_mul(0x100000001b3);
+        }
+    }
+
+    /// Hashes the blocks of the generated chunks, in generation order
+    fn generation_checksum(seed: WorldSeed, chunks: &[IVec3]) -> (u64, usize) {
+        let mut pending = PendingBlocks::default();
+        let mut hash = 0xcbf29ce484222325;
+        let mut block_count = 0;
+        for chunk_pos in chunks {
+            // The timestamp of the chunk is left out, it depends on the time of generation
+            let chunk = generate_chunk(*chunk_pos, seed.0, &mut pending);
+            fnv1a(
+                &mut hash,
+                &bincode::serialize(&chunk_pos.to_array()).unwrap(),
+            );
+            for (local_pos, block) in chunk.blocks.iter() {
+                fnv1a(
+                    &mut hash,
+                    &bincode::serialize(&(local_pos.to_array(), block)).unwrap(),
+                );
+            }
+            block_count += chunk.blocks.len();
+        }
+        (hash, block_count)
+    }
+
+    #[test]
+    fn generation_is_deterministic() {
+        let mut chunks = Vec::new();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                for y in 0..6 {
+                    chunks.push(IVec3::new(x, y, z));
+                }
+            }
+        }
+
+        let (hash, block_count) = generation_checksum(WorldSeed(42), &chunks);
+        assert!(block_count > 0);
+        // Changing this value changes the terrain of the existing worlds
+        assert_eq!(hash, 0x0835638b91130bf0, "the generated terrain changed");
+    }
+
+    #[test]
+    fn underground_chunks_have_no_surface_blocks() {
+        let mut pending = PendingBlocks::default();
+        for x in -1..=1 {
+            for z in -1..=1 {
+                let chunk = generate_chunk(IVec3::new(x, 1, z), 42, &mut pending);
+                assert!(!chunk.blocks.is_empty());
+                for (local_pos, block) in chunk.blocks.iter() {
+                    assert!(
+                        matches!(
+                            block.id,
+                            BlockId::Stone
+                                | BlockId::CoalOre
+                                | BlockId::IronOre
+                                | BlockId::GoldOre
+                                | BlockId::DiamondOre
+                        ),
+                        "{:?} at {:?} in chunk {} 1 {}",
+                        block.id,
+                        local_pos,
+                        x,
+                        z
+                    );
+                }
+            }
+        }
+    }
+}
@@ -1,22 +1,45 @@
 use bevy::prelude::*;
 use ron::de::from_str;
-use shared::world::data::{ServerWorldMap, WorldSeed};
-use shared::world::get_game_folder;
+use shared::world::data::{ServerChunk, ServerWorldMap, WorldSeed};
+use shared::world::{get_game_folder, BlockData, ChunkStorage};
 use shared::GameFolderPaths;
+use std::collections::HashMap;
 use std::fs;
 use std::path::Path;
 
-use crate::world::data::SAVE_PATH;
+use crate::world::data::{get_world_folder, LevelData, LEVEL_FILE_NAME, SAVE_PATH};
+use crate::world::region::RegionStorage;
+use crate::world::save::save_level_data;
 use std::path::PathBuf;
 
-#[derive(serde::Serialize, serde::Deserialize)]
 pub struct WorldData {
     pub seed: WorldSeed,
     pub map: ServerWorldMap,
     pub time: u64,
 }
 
-/// Charge les données combinées (carte et graine) d'un fichier
+/// Former save format : the whole world in a single `saves/<world>.ron` file
+#[derive(serde::Deserialize)]
+struct LegacyWorldData {
+    pub seed: WorldSeed,
+    pub map: LegacyWorldMap,
+    pub time: u64,
+}
+
+#[derive(serde::Deserialize)]
+struct LegacyWorldMap {
+    pub map: HashMap<IVec3, LegacyChunk>,
+}
+
+/// Chunks used to be stored as a map of local positions to blocks
+#[derive(serde::Deserialize)]
+struct LegacyChunk {
+    pub map: HashMap<IVec3, BlockData>,
+    pub ts: u64,
+}
+
+/// Charge les métadonnées du monde (graine et temps)\
+/// Les chunks sont chargés à la demande depuis les fichiers de région
 pub fn load_world_data(
     file_name: &str,
     app: &App,
@@ -24,11 +47,17 @@ pub fn load_world_data(
     // Obtenir le chemin du dossier de jeu
     let game_folder_path = app.world().get_resource::<GameFolderPaths>().unwrap();
 
-    // Construire le chemin complet du fichier
-    let file_path: PathBuf = get_game_folder(Some(game_folder_path))
+    let world_folder: PathBuf = get_world_folder(game_folder_path, file_name);
+    let file_path: PathBuf = world_folder.join(LEVEL_FILE_NAME);
+    let path: &Path = file_path.as_path();
+
+    // Convert saves from the former single file format
+    let legacy_file_path: PathBuf = get_game_folder(Some(game_folder_path))
         .join(SAVE_PATH)
         .join(format!("{file_name}.ron"));
-    let path: &Path = file_path.as_path();
+    if !path.exists() && legacy_file_path.exists() {
+        migrate_legacy_save(&legacy_file_path, &world_folder)?;
+    }
 
     // Vérifier si le fichier existe
     if !path.exists() {
@@ -48,8 +77,69 @@ pub fn load_world_data(
 
     // Lire le contenu du fichier
     let contents: String = fs::read_to_string(path)?;
-    let world_data: WorldData = from_str(&contents)?; // Désérialiser les données combinées
-    Ok(world_data)
+    let level_data: LevelData = from_str(&contents)?;
+    Ok(WorldData {
+        map: ServerWorldMap {
+            name: file_name.to_string(),
+            ..Default::default()
+        },
+        seed: level_data.seed,
+        time: level_data.time,
+    })
+}
+
+/// Converts a `saves/<world>.ron` file to the region format\
+/// The old file is kept, renamed to `<world>.ron.migrated`
+fn migrate_legacy_save(
+    legacy_file_path: &Path,
+    world_folder: &Path,
+) -> Result<(), Box<dyn std::error::Error>> {
+    info!(
+        "Migrating world save {} to the region format",
+        legacy_file_path.display()
+    );
+
+    let contents: String = fs::read_to_string(legacy_file_path)?;
+    let legacy_data: LegacyWorldData = from_str(&contents)?;
+
+    let mut world_map = ServerWorldMap::default();
+    for (chunk_pos, legacy_chunk) in legacy_data.map.map {
+        let mut chunk = ServerChunk {
+            blocks: ChunkStorage::default(),
+            ts: legacy_chunk.ts,
+        };
+        for (local_pos, block) in legacy_chunk.map {
+            chunk.blocks.insert(local_pos, block);
+        }
+        world_map.map.insert(chunk_pos, chunk);
+    }
+
+    let mut region_storage = RegionStorage::new(world_folder);
+    for chunk_pos in world_map.map.keys() {
+        region_storage.mark_dirty(chunk_pos);
+    }
+    let regions_count = region_storage.save_dirty_regions(&world_map)?;
+
+    // The level file is written last : if the migration is interrupted, it will run again
+    save_level_data(
+        &LevelData {
+            seed: legacy_data.seed,
+            time: legacy_data.time,
+        },
+        world_folder,
+    )?;
+
+    fs::rename(
+        legacy_file_path,
+        legacy_file_path.with_extension("ron.migrated"),
+    )?;
+
+    info!(
+        "World migrated: {} chunks written to {} regions",
+        world_map.map.len(),
+        regions_count
+    );
+    Ok(())
 }
 
 pub fn load_world_map(
@@ -1,38 +1,129 @@
-mod data;
+pub mod data;
+pub mod fluids;
 pub mod generation;
 pub mod load_from_file;
+pub mod player_data;
+pub mod region;
 pub mod save;
 
+use crate::init::TickCounter;
 use bevy::prelude::Event;
 use bevy::prelude::EventReader;
 use bevy::prelude::IVec3;
 use bevy::prelude::ResMut;
 use bevy::prelude::*;
+use fluids::FluidTicks;
+use player_data::PlayerDataStore;
+use region::RegionStorage;
+use shared::messages::{BlockChange, PlayerId};
+use shared::players::INTERACTION_DISTANCE;
+use shared::world::global_block_to_chunk_pos;
 use shared::world::BlockData;
+use shared::world::ItemStack;
 use shared::world::ServerWorldMap;
 
 #[derive(Event, Debug)]
 pub struct BlockInteractionEvent {
+    pub player_id: PlayerId,
     pub position: IVec3,
     pub block_type: Option<BlockData>, // None = suppression, Some = ajout
+    /// Inventory slot of the placed block
+    pub slot: u32,
 }
 
 pub fn handle_block_interactions(
     mut world_map: ResMut<ServerWorldMap>,
+    mut region_storage: ResMut<RegionStorage>,
+    mut fluid_ticks: ResMut<FluidTicks>,
+    mut player_data: ResMut<PlayerDataStore>,
+    ticker: Res<TickCounter>,
     mut events: EventReader<BlockInteractionEvent>,
 ) {
     for event in events.read() {
+        let current = world_map.get_block_by_coordinates(&event.position).copied();
+
+        if !is_in_reach(&world_map, event.player_id, &event.position) {
+            warn!(
+                "Player {} tried to modify the block at {:?}, out of its reach",
+                event.player_id, event.position
+            );
+            send_block_back(&mut world_map, event.position, current);
+            continue;
+        }
+
+        let Some(inventory) = player_data.inventory_mut(event.player_id) else {
+            continue;
+        };
+
         match &event.block_type {
             Some(block) => {
-                // Ajouter un bloc
+                // Un bloc ne peut remplacer que l'air ou un liquide
+                if current.is_some_and(|current| !current.id.is_replaceable()) {
+                    debug!(
+                        "Player {} tried to place {:?} in {:?}",
+                        event.player_id, block.id, current
+                    );
+                    send_block_back(&mut world_map, event.position, current);
+                    continue;
+                }
+
+                // Ajouter un bloc, seulement si le joueur le possède
+                if !inventory.take_block(event.slot, block.id) {
+                    warn!(
+                        "Player {} tried to place {:?} without holding it",
+                        event.player_id, block.id
+                    );
+                    send_block_back(&mut world_map, event.position, current);
+                    continue;
+                }
                 world_map.set_block(&event.position, *block);
                 debug!("Block added at {:?}: {:?}", event.position, block);
             }
             None => {
                 // Supprimer un bloc
-                world_map.remove_block_by_coordinates(&event.position);
+                let Some(block) = world_map.remove_block_by_coordinates(&event.position) else {
+                    continue;
+                };
                 info!("Block removed at {:?}", event.position);
+
+                // The server decides what the player gets from the block
+                for (item_id, nb) in block.id.get_drops(1) {
+                    let lost = inventory.add_item_to_inventory(ItemStack {
+                        item_id,
+                        item_type: item_id.get_default_type(),
+                        nb,
+                    });
+                    if lost > 0 {
+                        debug!(
+                            "Inventory of player {} is full, {} {:?} lost",
+                            event.player_id, lost, item_id
+                        );
+                    }
+                }
             }
         }
+
+        region_storage.mark_dirty(&global_block_to_chunk_pos(&event.position));
+        // Les liquides voisins peuvent s'écouler dans le bloc modifié
+        fluid_ticks.schedule_around(&event.position, ticker.tick + 1);
     }
 }
+
+/// The client may see the player a bit further than the server does, because of latency
+const REACH_TOLERANCE: f32 = 2.;
+
+/// Whether the block is close enough to the player for it to be modified
+fn is_in_reach(world_map: &ServerWorldMap, player_id: PlayerId, position: &IVec3) -> bool {
+    let Some(player_position) = world_map.player_positions.get(&player_id) else {
+        return false;
+    };
+    let block_center = position.as_vec3() + Vec3::splat(0.5);
+    block_center.distance(*player_position) <= INTERACTION_DISTANCE + REACH_TOLERANCE
+}
+
+/// The client already modified the block, it gets the real block back
+fn send_block_back(world_map: &mut ServerWorldMap, position: IVec3, block: Option<BlockData>) {
+    world_map
+        .block_changes
+        .push(BlockChange { position, block });
+}
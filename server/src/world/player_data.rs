@@ -0,0 +1,152 @@
+use crate::config::ServerSettings;
+use crate::world::save::write_atomic;
+use bevy::prelude::*;
+use ron::ser::PrettyConfig;
+use serde::{Deserialize, Serialize};
+use shared::messages::{PlayerId, PlayerOrientation};
+use shared::players::{GameMode, Inventory, Player};
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+pub const PLAYER_DATA_FOLDER_NAME: &str = "players";
+
+/// Saved state of a player, stored in `saves/<world>/players/<username>.ron`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PlayerData {
+    pub position: Vec3,
+    pub orientation: PlayerOrientation,
+    pub is_flying: bool,
+    pub game_mode: GameMode,
+    pub spawn_point: Vec3,
+    pub inventory: Inventory,
+}
+
+impl PlayerData {
+    /// State of a player joining the world for the first time
+    pub fn new(settings: &ServerSettings) -> Self {
+        Self {
+            position: settings.spawn_point,
+            orientation: PlayerOrientation::default(),
+            is_flying: false,
+            game_mode: settings.default_game_mode,
+            spawn_point: settings.spawn_point,
+            inventory: Inventory::new(),
+        }
+    }
+}
+
+/// Data of the players logged in, written to disk when they leave and on every save
+#[derive(Resource)]
+pub struct PlayerDataStore {
+    folder: PathBuf,
+    pub players: HashMap<PlayerId, PlayerData>,
+    /// Players whose inventory changed since it was last sent to them
+    changed_inventories: HashSet<PlayerId>,
+}
+
+impl PlayerDataStore {
+    pub fn new(world_folder: &Path) -> Self {
+        Self {
+            folder: world_folder.join(PLAYER_DATA_FOLDER_NAME),
+            players: HashMap::new(),
+            changed_inventories: HashSet::new(),
+        }
+    }
+
+    /// Inventory of a player logged in, which is sent again to its client after the change
+    pub fn inventory_mut(&mut self, player_id: PlayerId) -> Option<&mut Inventory> {
+        let data = self.players.get_mut(&player_id)?;
+        self.changed_inventories.insert(player_id);
+        Some(&mut data.inventory)
+    }
+
+    /// Players whose inventory changed, with their new inventory
+    pub fn take_changed_inventories(&mut self) -> Vec<(PlayerId, Inventory)> {
+        let changed = std::mem::take(&mut self.changed_inventories);
+        changed
+            .into_iter()
+            .filter_map(|id| Some((id, self.players.get(&id)?.inventory.clone())))
+            .collect()
+    }
+
+    /// Forgets a player who left, once its data has been saved
+    pub fn unload(&mut self, player_id: PlayerId) {
+        self.players.remove(&player_id);
+        self.changed_inventories.remove(&player_id);
+    }
+
+    /// Usernames are escaped, so that any of them gives a distinct and valid file name
+    fn player_path(&self, username: &str) -> PathBuf {
+        let mut file_name = String::new();
+        for byte in username.bytes() {
+            if byte.is_ascii_alphanumeric() || byte == b'-' {
+                file_name.push(byte as char);
+            } else {
+                file_name.push_str(&format!("_{:02x}", byte));
+            }
+        }
+        self.folder.join(format!("{}.ron", file_name))
+    }
+
+    /// Reads the data of a player logging in, or creates it if the player never joined this world
+    pub fn load(
+        &mut self,
+        player_id: PlayerId,
+        username: &str,
+        settings: &ServerSettings,
+    ) -> &PlayerData {
+        let path = self.player_path(username);
+        let data = if path.exists() {
+            match fs::read_to_string(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|contents| ron::from_str(&contents).map_err(|e| e.to_string()))
+            {
+                Ok(data) => data,
+                Err(e) => {
+                    error!(
+                        "Failed to load the data of {} from {:?}: {}",
+                        username, path, e
+                    );
+                    // Kept aside, so the next save does not overwrite what may still be recovered
+                    let corrupt_path = path.with_extension("ron.corrupt");
+                    match fs::rename(&path, &corrupt_path) {
+                        Ok(()) => warn!("Unreadable player data moved to {:?}", corrupt_path),
+                        Err(e) => error!("Failed to move {:?} aside: {}", path, e),
+                    }
+                    PlayerData::new(settings)
+                }
+            }
+        } else {
+            PlayerData::new(settings)
+        };
+
+        self.players.insert(player_id, data);
+        &self.players[&player_id]
+    }
+
+    /// Copies the current state of the player in its data, then writes it to disk
+    pub fn save(
+        &mut self,
+        player: &Player,
+        position: Option<Vec3>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.player_path(&player.name);
+        let Some(data) = self.players.get_mut(&player.id) else {
+            return Ok(());
+        };
+
+        if let Some(position) = position {
+            data.position = position;
+        }
+        data.orientation = player.orientation;
+        data.is_flying = player.is_flying;
+        data.game_mode = player.game_mode;
+        data.spawn_point = player.spawn_point;
+
+        fs::create_dir_all(&self.folder)?;
+        let serialized = ron::ser::to_string_pretty(data, PrettyConfig::new())?;
+        write_atomic(&path, serialized.as_bytes())?;
+        Ok(())
+    }
+}
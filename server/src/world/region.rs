@@ -0,0 +1,413 @@
+use bevy::prelude::*;
+use bincode::Options;
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use shared::world::{ServerChunk, ServerWorldMap};
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::world::data::REGION_FOLDER_NAME;
+
+/// Number of chunks along the X and Z axis of a region
+pub const REGION_SIZE: i32 = 32;
+
+const REGION_MAGIC: [u8; 4] = *b"RCRG";
+const REGION_VERSION: u32 = 3;
+/// magic + version + number of entries
+const HEADER_SIZE: u64 = 12;
+/// local x (u8) + local z (u8) + chunk y (i32) + offset (u64) + length (u32)
+const ENTRY_SIZE: u64 = 18;
+
+pub fn chunk_to_region_pos(chunk_pos: &IVec3) -> IVec2 {
+    IVec2::new(
+        chunk_pos.x.div_euclid(REGION_SIZE),
+        chunk_pos.z.div_euclid(REGION_SIZE),
+    )
+}
+
+/// Location of a compressed chunk inside a region file
+#[derive(Debug, Clone, Copy)]
+struct ChunkLocation {
+    offset: u64,
+    length: u32,
+}
+
+/// Offset table of a region file, indexed by chunk position
+#[derive(Debug, Default)]
+struct RegionIndex {
+    chunks: HashMap<IVec3, ChunkLocation>,
+}
+
+/// Binary chunk storage, split in region files of `REGION_SIZE`x`REGION_SIZE` chunks
+///
+/// A region file is made of a header, an offset table and the chunks themselves,
+/// each one serialized and compressed separately so it can be loaded on its own.\
+/// Only regions containing modified chunks are rewritten when saving.
+#[derive(Resource)]
+pub struct RegionStorage {
+    folder: PathBuf,
+    /// Offset tables of the region files read so far
+    indexes: HashMap<IVec2, RegionIndex>,
+    dirty_regions: HashSet<IVec2>,
+}
+
+impl RegionStorage {
+    pub fn new(world_folder: &Path) -> Self {
+        Self {
+            folder: world_folder.join(REGION_FOLDER_NAME),
+            indexes: HashMap::new(),
+            dirty_regions: HashSet::new(),
+        }
+    }
+
+    fn region_path(&self, region_pos: &IVec2) -> PathBuf {
+        self.folder
+            .join(format!("r.{}.{}.bin", region_pos.x, region_pos.y))
+    }
+
+    fn get_index(&mut self, region_pos: &IVec2) -> io::Result<&RegionIndex> {
+        if !self.indexes.contains_key(region_pos) {
+            let index = read_region_index(&self.region_path(region_pos), region_pos)?;
+            self.indexes.insert(*region_pos, index);
+        }
+        Ok(&self.indexes[region_pos])
+    }
+
+    /// Marks the region containing this chunk as needing to be written on next save
+    pub fn mark_dirty(&mut self, chunk_pos: &IVec3) {
+        self.dirty_regions.insert(chunk_to_region_pos(chunk_pos));
+    }
+
+    /// Loads a single chunk from its region file, if it was saved before
+    pub fn load_chunk(&mut self, chunk_pos: &IVec3) -> Option<ServerChunk> {
+        let region_pos = chunk_to_region_pos(chunk_pos);
+        let location = match self.get_index(&region_pos) {
+            Ok(index) => *index.chunks.get(chunk_pos)?,
+            Err(e) => {
+                error!("Failed to read region {:?}: {}", region_pos, e);
+                return None;
+            }
+        };
+
+        let path = self.region_path(&region_pos);
+        let chunk = read_chunk_bytes(&path, &location).and_then(|bytes| {
+            bincode::options()
+                .deserialize_from(ZlibDecoder::new(&bytes[..]))
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+        });
+
+        match chunk {
+            Ok(chunk) => Some(chunk),
+            Err(e) => {
+                error!("Failed to load chunk {:?} from region: {}", chunk_pos, e);
+                None
+            }
+        }
+    }
+
+    /// Writes every dirty region to disk, returns the number of regions written\
+    /// A region which could not be written stays dirty, the other ones are still written
+    pub fn save_dirty_regions(
+        &mut self,
+        world_map: &ServerWorldMap,
+    ) -> Result<usize, Box<dyn std::error::Error>> {
+        if self.dirty_regions.is_empty() {
+            return Ok(0);
+        }
+
+        fs::create_dir_all(&self.folder)?;
+
+        // Group loaded chunks by region, only keeping the regions to write
+        let mut regions: HashMap<IVec2, Vec<(IVec3, &ServerChunk)>> = HashMap::new();
+        for (pos, chunk) in world_map.map.iter() {
+            let region_pos = chunk_to_region_pos(pos);
+            if self.dirty_regions.contains(&region_pos) {
+                regions.entry(region_pos).or_default().push((*pos, chunk));
+            }
+        }
+
+        let dirty_regions: Vec<IVec2> = self.dirty_regions.iter().copied().collect();
+        let mut written = 0;
+        let mut errors = Vec::new();
+        for region_pos in dirty_regions.iter() {
+            let loaded_chunks = regions.remove(region_pos).unwrap_or_default();
+            match self.write_region(region_pos, loaded_chunks) {
+                Ok(()) => {
+                    self.dirty_regions.remove(region_pos);
+                    written += 1;
+                }
+                Err(e) => errors.push(format!("region {:?}: {}", region_pos, e)),
+            }
+        }
+
+        if !errors.is_empty() {
+            return Err(format!(
+                "Failed to write {} region(s) : {}",
+                errors.len(),
+                errors.join(", ")
+            )
+            .into());
+        }
+        Ok(written)
+    }
+
+    fn write_region(
+        &mut self,
+        region_pos: &IVec2,
+        loaded_chunks: Vec<(IVec3, &ServerChunk)>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let path = self.region_path(region_pos);
+
+        let mut blobs: Vec<(IVec3, Vec<u8>)> = Vec::with_capacity(loaded_chunks.len());
+        for (pos, chunk) in loaded_chunks.iter() {
+            let mut encoder = ZlibEncoder::new(Vec::new(), Compression::fast());
+            bincode::options().serialize_into(&mut encoder, chunk)?;
+            blobs.push((*pos, encoder.finish()?));
+        }
+
+        // Chunks saved previously but never loaded since are copied as is
+        let loaded_positions: HashSet<IVec3> = loaded_chunks.iter().map(|(pos, _)| *pos).collect();
+        let previous: Vec<(IVec3, ChunkLocation)> = self
+            .get_index(region_pos)?
+            .chunks
+            .iter()
+            .filter(|(pos, _)| !loaded_positions.contains(*pos))
+            .map(|(pos, location)| (*pos, *location))
+            .collect();
+        for (pos, location) in previous {
+            blobs.push((pos, read_chunk_bytes(&path, &location)?));
+        }
+
+        // Write to a temporary file first, so a crash cannot corrupt the existing region
+        let tmp_path = path.with_extension("bin.tmp");
+        let mut index = RegionIndex::default();
+        {
+            let mut writer = BufWriter::new(File::create(&tmp_path)?);
+            writer.write_all(&REGION_MAGIC)?;
+            writer.write_all(&REGION_VERSION.to_le_bytes())?;
+            writer.write_all(&(blobs.len() as u32).to_le_bytes())?;
+
+            let mut offset = HEADER_SIZE + ENTRY_SIZE * blobs.len() as u64;
+            for (pos, blob) in blobs.iter() {
+                let location = ChunkLocation {
+                    offset,
+                    length: blob.len() as u32,
+                };
+                writer.write_all(&[
+                    pos.x.rem_euclid(REGION_SIZE) as u8,
+                    pos.z.rem_euclid(REGION_SIZE) as u8,
+                ])?;
+                writer.write_all(&pos.y.to_le_bytes())?;
+                writer.write_all(&location.offset.to_le_bytes())?;
+                writer.write_all(&location.length.to_le_bytes())?;
+                index.chunks.insert(*pos, location);
+                offset += blob.len() as u64;
+            }
+
+            for (_, blob) in blobs.iter() {
+                writer.write_all(blob)?;
+            }
+            writer.into_inner()?.sync_all()?;
+        }
+        fs::rename(&tmp_path, &path)?;
+
+        self.indexes.insert(*region_pos, index);
+        trace!(
+            "Region {:?} written, {} chunks",
+            region_pos,
+            self.indexes[region_pos].chunks.len()
+        );
+        Ok(())
+    }
+}
+
+fn read_region_index(path: &Path, region_pos: &IVec2) -> io::Result<RegionIndex> {
+    let mut index = RegionIndex::default();
+    if !path.exists() {
+        return Ok(index);
+    }
+
+    let mut reader = BufReader::new(File::open(path)?);
+    let mut header = [0u8; HEADER_SIZE as usize];
+    reader.read_exact(&mut header)?;
+    if header[0..4] != REGION_MAGIC {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "not a region file",
+        ));
+    }
+    let version = u32::from_le_bytes(header[4..8].try_into().unwrap());
+    if version != REGION_VERSION {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("unsupported region version {}", version),
+        ));
+    }
+    let count = u32::from_le_bytes(header[8..12].try_into().unwrap());
+
+    let mut entry = [0u8; ENTRY_SIZE as usize];
+    for _ in 0..count {
+        reader.read_exact(&mut entry)?;
+        let pos = IVec3::new(
+            region_pos.x * REGION_SIZE + entry[0] as i32,
+            i32::from_le_bytes(entry[2..6].try_into().unwrap()),
+            region_pos.y * REGION_SIZE + entry[1] as i32,
+        );
+        index.chunks.insert(
+            pos,
+            ChunkLocation {
+                offset: u64::from_le_bytes(entry[6..14].try_into().unwrap()),
+                length: u32::from_le_bytes(entry[14..18].try_into().unwrap()),
+            },
+        );
+    }
+
+    Ok(index)
+}
+
+fn read_chunk_bytes(path: &Path, location: &ChunkLocation) -> io::Result<Vec<u8>> {
+    let mut file = File::open(path)?;
+    file.seek(SeekFrom::Start(location.offset))?;
+    let mut bytes = vec![0u8; location.length as usize];
+    file.read_exact(&mut bytes)?;
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shared::world::{BlockData, BlockDirection, BlockId};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    /// Empty folder in the temporary directory, removed by the test
+    fn test_folder(name: &str) -> PathBuf {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        let folder = std::env::temp_dir().join(format!(
+            "region-test-{}-{}-{}",
+            name,
+            std::process::id(),
+            nanos
+        ));
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn test_chunk(block_id: BlockId, ts: u64) -> ServerChunk {
+        let mut chunk = ServerChunk {
+            ts,
+            ..Default::default()
+        };
+        for x in 0..16 {
+            chunk.blocks.insert(
+                IVec3::new(x, x % 4, 15 - x),
+                BlockData::new(block_id, false, BlockDirection::Front),
+            );
+        }
+        chunk
+    }
+
+    fn assert_same_chunk(loaded: Option<ServerChunk>, expected: &ServerChunk) {
+        let loaded = loaded.expect("chunk not found in the region");
+        assert_eq!(loaded.blocks, expected.blocks);
+        assert_eq!(loaded.ts, expected.ts);
+    }
+
+    #[test]
+    fn dirty_chunks_are_reloaded() {
+        let folder = test_folder("round-trip");
+
+        // Chunks spread over three regions, including negative coordinates
+        let mut world_map = ServerWorldMap::default();
+        world_map
+            .map
+            .insert(IVec3::new(0, 0, 0), test_chunk(BlockId::Stone, 1));
+        world_map
+            .map
+            .insert(IVec3::new(1, 2, 3), test_chunk(BlockId::Dirt, 2));
+        world_map
+            .map
+            .insert(IVec3::new(-1, -4, -1), test_chunk(BlockId::Sand, 3));
+        world_map
+            .map
+            .insert(IVec3::new(40, 1, 5), test_chunk(BlockId::Glass, 4));
+
+        let mut storage = RegionStorage::new(&folder);
+        for chunk_pos in world_map.map.keys() {
+            storage.mark_dirty(chunk_pos);
+        }
+        assert_eq!(storage.save_dirty_regions(&world_map).unwrap(), 3);
+        // Nothing left to write
+        assert_eq!(storage.save_dirty_regions(&world_map).unwrap(), 0);
+
+        let mut storage = RegionStorage::new(&folder);
+        for (chunk_pos, chunk) in world_map.map.iter() {
+            assert_same_chunk(storage.load_chunk(chunk_pos), chunk);
+        }
+        assert!(storage.load_chunk(&IVec3::new(2, 0, 0)).is_none());
+        assert!(storage.load_chunk(&IVec3::new(100, 0, 100)).is_none());
+
+        // Only one chunk of the region is loaded and modified, the others are kept as is
+        let modified = test_chunk(BlockId::Cobblestone, 5);
+        let mut partial_map = ServerWorldMap::default();
+        partial_map
+            .map
+            .insert(IVec3::new(0, 0, 0), modified.clone());
+        storage.mark_dirty(&IVec3::new(0, 0, 0));
+        assert_eq!(storage.save_dirty_regions(&partial_map).unwrap(), 1);
+
+        let mut storage = RegionStorage::new(&folder);
+        assert_same_chunk(storage.load_chunk(&IVec3::new(0, 0, 0)), &modified);
+        assert_same_chunk(
+            storage.load_chunk(&IVec3::new(1, 2, 3)),
+            &world_map.map[&IVec3::new(1, 2, 3)],
+        );
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+
+    #[test]
+    fn failed_regions_stay_dirty() {
+        let folder = test_folder("failure");
+
+        let mut world_map = ServerWorldMap::default();
+        world_map
+            .map
+            .insert(IVec3::new(0, 0, 0), test_chunk(BlockId::Stone, 1));
+        world_map
+            .map
+            .insert(IVec3::new(40, 1, 5), test_chunk(BlockId::Glass, 2));
+
+        // A folder in place of the region file makes its write fail
+        let mut storage = RegionStorage::new(&folder);
+        let blocked_path = storage.region_path(&IVec2::new(0, 0));
+        fs::create_dir_all(&blocked_path).unwrap();
+        for chunk_pos in world_map.map.keys() {
+            storage.mark_dirty(chunk_pos);
+        }
+        assert!(storage.save_dirty_regions(&world_map).is_err());
+
+        // The other region was still written
+        let mut reloaded = RegionStorage::new(&folder);
+        assert_same_chunk(
+            reloaded.load_chunk(&IVec3::new(40, 1, 5)),
+            &world_map.map[&IVec3::new(40, 1, 5)],
+        );
+
+        // The failed region is written by the next save
+        fs::remove_dir_all(&blocked_path).unwrap();
+        assert_eq!(storage.save_dirty_regions(&world_map).unwrap(), 1);
+        let mut reloaded = RegionStorage::new(&folder);
+        assert_same_chunk(
+            reloaded.load_chunk(&IVec3::new(0, 0, 0)),
+            &world_map.map[&IVec3::new(0, 0, 0)],
+        );
+
+        fs::remove_dir_all(&folder).unwrap();
+    }
+}
@@ -1,31 +1,96 @@
-use crate::init::ServerTime;
+use crate::config::ServerSettings;
+use crate::init::{ServerLobby, ServerTime};
+use crate::world::data::{get_world_folder, LevelData, LEVEL_FILE_NAME};
+use crate::world::generation::PendingBlocks;
+use crate::world::player_data::PlayerDataStore;
+use crate::world::region::RegionStorage;
 use bevy::prelude::*;
 use ron::ser::PrettyConfig;
-use shared::world::get_game_folder;
 use shared::world::ServerWorldMap;
 use shared::world::WorldSeed;
 use shared::GameFolderPaths;
-use std::{fs::File, io::Write, path::Path};
+use std::thread::{self, JoinHandle};
+use std::time::{SystemTime, UNIX_EPOCH};
+use std::{fs, fs::File, io, io::Write, path::Path, path::PathBuf};
+
+pub const BACKUP_FOLDER_NAME: &str = "backups";
 
 #[derive(Event)]
 pub struct SaveRequestEvent;
 
-use crate::world::data::SAVE_PATH;
+/// Backups copy the whole world : they are made in a background thread, at most every `backup_interval` seconds
+#[derive(Default)]
+pub struct BackupState {
+    /// Elapsed time when the last backup started, in seconds
+    last_backup: Option<f64>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl BackupState {
+    fn start_if_due(&mut self, world_folder: PathBuf, settings: &ServerSettings, now: f64) {
+        if settings.backup_count == 0 || settings.backup_interval == 0 {
+            return;
+        }
+        if self
+            .last_backup
+            .is_some_and(|last| now - last < settings.backup_interval as f64)
+        {
+            return;
+        }
+        // A slow disk may still be copying the previous backup
+        if self
+            .thread
+            .as_ref()
+            .is_some_and(|thread| !thread.is_finished())
+        {
+            warn!("Previous backup not finished, skipping this one");
+            return;
+        }
+
+        self.last_backup = Some(now);
+        let backup_count = settings.backup_count;
+        self.thread = Some(thread::spawn(move || {
+            if let Err(e) = backup_world(&world_folder, backup_count) {
+                error!("Failed to back up the world: {}", e);
+            }
+        }));
+    }
+}
+
+/// Requests a save every `autosave_interval` seconds
+pub fn autosave_system(
+    time: Res<Time>,
+    settings: Res<ServerSettings>,
+    mut timer: Local<Option<Timer>>,
+    mut ev_save: EventWriter<SaveRequestEvent>,
+) {
+    if settings.autosave_interval == 0 {
+        return;
+    }
 
-#[derive(serde::Serialize, serde::Deserialize)]
-pub struct WorldData {
-    pub seed: WorldSeed,
-    pub map: ServerWorldMap,
-    pub time: u64,
+    let timer = timer.get_or_insert_with(|| {
+        Timer::from_seconds(settings.autosave_interval as f32, TimerMode::Repeating)
+    });
+    timer.tick(time.delta());
+    if timer.just_finished() {
+        debug!("Autosave");
+        ev_save.send(SaveRequestEvent);
+    }
 }
 
-// System to save the world when "L" is pressed
+// System to save the world when requested by a client, a command, the autosave or the shutdown
 pub fn save_world_system(
-    world_map: ResMut<ServerWorldMap>,
+    world_map: Res<ServerWorldMap>,
     world_seed: Res<WorldSeed>,
     game_folder_path: Res<GameFolderPaths>,
-    time: Res<ServerTime>,
+    (time, real_time): (Res<ServerTime>, Res<Time>),
+    settings: Res<ServerSettings>,
+    lobby: Res<ServerLobby>,
+    mut region_storage: ResMut<RegionStorage>,
+    pending_blocks: Res<PendingBlocks>,
+    mut player_data: ResMut<PlayerDataStore>,
     mut event: EventReader<SaveRequestEvent>,
+    mut backup: Local<BackupState>,
 ) {
     // Reads all events to prevent them from being queued forever and repeatedly request a save
     let mut save_requested = false;
@@ -33,35 +98,137 @@ pub fn save_world_system(
         save_requested = true;
     }
 
-    // If a save was requested by the user
+    // If a save was requested
     if save_requested {
-        let world_data = WorldData {
-            map: world_map.clone(),
+        let level_data = LevelData {
             seed: world_seed.clone(),
             time: time.0,
         };
 
-        // define save file path
-        let save_file_path = format!(
-            "{}{}.ron",
-            get_game_folder(Some(&game_folder_path))
-                .join(SAVE_PATH)
-                .display(),
-            world_map.name
-        );
-
-        // save seed and world data
-        if let Err(e) = save_world_data(&world_data, &save_file_path) {
-            error!("Failed to save world data: {}", e);
+        let world_folder = get_world_folder(&game_folder_path, &world_map.name);
+
+        // save seed and time, the modified regions and the structure blocks waiting for their chunk
+        // Each part is saved even when another one fails, so that as little as possible is lost
+        let mut world_saved = true;
+        if let Err(e) = save_level_data(&level_data, &world_folder) {
+            error!("Failed to save the level data: {}", e);
+            world_saved = false;
+        }
+        let regions_count = match region_storage.save_dirty_regions(&world_map) {
+            Ok(regions_count) => regions_count,
+            Err(e) => {
+                error!("Failed to save the regions: {}", e);
+                world_saved = false;
+                0
+            }
+        };
+        if let Err(e) = pending_blocks.save(&world_folder) {
+            error!("Failed to save the pending blocks: {}", e);
+            world_saved = false;
+        }
+        if world_saved {
+            info!(
+                "World data saved successfully! Name: {}, regions written: {}",
+                world_map.name, regions_count
+            );
+        }
+
+        // The players still logged in are saved too, the others were saved when they left
+        for (id, player) in lobby.players.iter() {
+            let position = world_map.player_positions.get(id).copied();
+            if let Err(e) = player_data.save(player, position) {
+                error!("Failed to save the data of {}: {}", player.name, e);
+            }
+        }
+
+        // An incomplete world is not backed up, the previous backups are kept instead
+        if world_saved {
+            backup.start_if_due(world_folder, &settings, real_time.elapsed_seconds_f64());
+        }
+    }
+}
+
+/// Writes a file through a temporary one, so that a crash while saving never leaves it truncated
+pub fn write_atomic(path: &Path, contents: &[u8]) -> io::Result<()> {
+    let mut tmp_path = path.as_os_str().to_owned();
+    tmp_path.push(".tmp");
+    let tmp_path = PathBuf::from(tmp_path);
+
+    {
+        let mut file = File::create(&tmp_path)?;
+        file.write_all(contents)?;
+        file.sync_all()?;
+    }
+    fs::rename(&tmp_path, path)
+}
+
+/// Copies the saved files of the world in `backups/<timestamp>`, and removes the oldest copies
+/// to only keep `backup_count` of them\
+/// The copy is made in `backups/<timestamp>.tmp` first, a backup interrupted by the end of the server is never kept
+fn backup_world(
+    world_folder: &Path,
+    backup_count: usize,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let backups_folder = world_folder.join(BACKUP_FOLDER_NAME);
+    fs::create_dir_all(&backups_folder)?;
+
+    // Leftovers of interrupted backups
+    for entry in fs::read_dir(&backups_folder)?.filter_map(|entry| entry.ok()) {
+        if entry
+            .path()
+            .extension()
+            .is_some_and(|extension| extension == "tmp")
+        {
+            fs::remove_dir_all(entry.path())?;
+        }
+    }
+
+    let timestamp = SystemTime::now().duration_since(UNIX_EPOCH)?.as_millis();
+    let backup_folder = backups_folder.join(timestamp.to_string());
+    let tmp_folder = backup_folder.with_extension("tmp");
+    copy_folder(world_folder, &tmp_folder, &backups_folder)?;
+    fs::rename(&tmp_folder, &backup_folder)?;
+
+    let mut backups: Vec<(u128, PathBuf)> = fs::read_dir(&backups_folder)?
+        .filter_map(|entry| entry.ok())
+        .filter_map(|entry| {
+            let timestamp = entry.file_name().to_str()?.parse::<u128>().ok()?;
+            Some((timestamp, entry.path()))
+        })
+        .collect();
+    backups.sort();
+
+    let excess = backups.len().saturating_sub(backup_count);
+    for (_, path) in backups.iter().take(excess) {
+        fs::remove_dir_all(path)?;
+    }
+    debug!("World backed up, {} backups kept", backups.len() - excess);
+    Ok(())
+}
+
+/// Copies a folder recursively, except the `skipped` one and the temporary files
+fn copy_folder(from: &Path, to: &Path, skipped: &Path) -> io::Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let path = entry.path();
+        if path == skipped || path.extension().is_some_and(|extension| extension == "tmp") {
+            continue;
+        }
+
+        let destination = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_folder(&path, &destination, skipped)?;
         } else {
-            info!("World data saved successfully! Name: {}", world_map.name);
+            fs::copy(&path, &destination)?;
         }
     }
+    Ok(())
 }
 
-pub fn save_world_data(
-    world_data: &WorldData,
-    file_path: &str,
+pub fn save_level_data(
+    level_data: &LevelData,
+    world_folder: &Path,
 ) -> Result<(), Box<dyn std::error::Error>> {
     // configure RON serialization
     let pretty_config = PrettyConfig::new()
@@ -69,11 +236,11 @@ pub fn save_world_data(
         .with_separate_tuple_members(true)
         .with_enumerate_arrays(true);
 
-    // serialize combined data (map + seed)
-    let serialized = ron::ser::to_string_pretty(world_data, pretty_config)?;
-    let path = Path::new(file_path);
-    let mut file = File::create(path)?;
-    file.write_all(serialized.as_bytes())?;
-    info!("World data saved to {}", file_path);
+    // serialize world metadata (seed + time)
+    let serialized = ron::ser::to_string_pretty(level_data, pretty_config)?;
+    fs::create_dir_all(world_folder)?;
+    let file_path = world_folder.join(LEVEL_FILE_NAME);
+    write_atomic(&file_path, serialized.as_bytes())?;
+    info!("World data saved to {}", file_path.display());
     Ok(())
 }
@@ -1,9 +1,11 @@
+use std::net::SocketAddr;
 use std::time::Duration;
 
 use bevy::prelude::Resource;
 use bevy_renet::renet::{ChannelConfig, ConnectionConfig, SendType};
 
 pub mod messages;
+pub mod players;
 pub mod world;
 
 #[derive(Resource, Debug, Clone)]
@@ -16,8 +18,25 @@ pub struct GameFolderPaths {
 pub struct GameServerConfig {
     pub world_name: String,
     pub is_solo: bool,
+    /// Unsecure authentication is used when `None`, as in solo
+    pub secure: Option<SecureAuthConfig>,
 }
 
+/// Secure netcode authentication, clients need a connect token signed with the private key
+#[derive(Debug, Clone)]
+pub struct SecureAuthConfig {
+    pub private_key: [u8; 32],
+    /// Address the clients connect to, written in their connect tokens
+    pub public_address: SocketAddr,
+    /// Runs a token service next to the server, as a stand-in for a real authentication service
+    pub run_token_service: bool,
+}
+
+/// The token service listens on the port of the game server plus this offset
+pub const TOKEN_SERVICE_PORT_OFFSET: u16 = 1;
+
+/// Netcode protocol id, never changed\
+/// Incompatible versions are detected by the handshake, to display a clear error
 pub const PROTOCOL_ID: u64 = 0;
 pub const CHUNK_SIZE: i32 = 16;
 
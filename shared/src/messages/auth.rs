@@ -1,6 +1,9 @@
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 
 use super::PlayerSpawnEvent;
+use crate::players::Inventory;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct AuthRegisterRequest {
@@ -8,8 +11,41 @@ pub struct AuthRegisterRequest {
 }
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
-pub struct AuthRegisterResponse {
-    pub username: String,
-    pub session_token: u128,
-    pub spawn_event: PlayerSpawnEvent,
+pub enum AuthRegisterResponse {
+    Accepted {
+        username: String,
+        session_token: u128,
+        spawn_event: PlayerSpawnEvent,
+        /// Inventory saved when the player last left this world
+        inventory: Inventory,
+    },
+    /// The client is disconnected by the server shortly after
+    Rejected(AuthRejectionReason),
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy)]
+pub enum AuthRejectionReason {
+    UsernameTaken,
+    ServerFull,
+    Banned,
+    VersionMismatch,
+    NotWhitelisted,
+    /// The connect token does not hold a valid username
+    InvalidToken,
+}
+
+impl fmt::Display for AuthRejectionReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let reason = match self {
+            AuthRejectionReason::UsernameTaken => "This username is already used on the server",
+            AuthRejectionReason::ServerFull => "The server is full",
+            AuthRejectionReason::Banned => "You are banned from this server",
+            AuthRejectionReason::VersionMismatch => {
+                "The versions of the client and the server do not match"
+            }
+            AuthRejectionReason::NotWhitelisted => "You are not whitelisted on this server",
+            AuthRejectionReason::InvalidToken => "Your connect token is not valid",
+        };
+        write!(f, "{}", reason)
+    }
 }
@@ -1,14 +1,47 @@
 use bevy::prelude::Resource;
 use serde::{Deserialize, Serialize};
 
+/// Number of messages kept by the server, and replayed to the players joining the game
+pub const CHAT_HISTORY_SIZE: usize = 100;
+
+/// Assigned by the server, increasing in the order messages were received
+pub type ChatMessageId = u64;
+
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ChatMessage {
+    pub id: ChatMessageId,
     pub author_name: String,
     pub date: u64, // timestamp ms
     pub content: String,
 }
 
+/// Message written by a player, the server fills in the author and the date
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct ChatMessageRequest {
+    pub content: String,
+}
+
 #[derive(Resource, Default, Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct ChatConversation {
     pub messages: Vec<ChatMessage>,
 }
+
+impl ChatConversation {
+    pub fn last_id(&self) -> Option<ChatMessageId> {
+        self.messages.last().map(|message| message.id)
+    }
+
+    /// Adds a message if it was not received yet, and forgets the oldest ones past `CHAT_HISTORY_SIZE`
+    pub fn push(&mut self, message: ChatMessage) -> bool {
+        if self.last_id().is_some_and(|last_id| message.id <= last_id) {
+            return false;
+        }
+
+        self.messages.push(message);
+        if self.messages.len() > CHAT_HISTORY_SIZE {
+            let excess = self.messages.len() - CHAT_HISTORY_SIZE;
+            self.messages.drain(..excess);
+        }
+        true
+    }
+}
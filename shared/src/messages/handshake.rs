@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// Version of the network protocol\
+/// Must be increased at each incompatible change of the messages or of their channels
+pub const PROTOCOL_VERSION: u32 = 13;
+
+/// Version of the game, displayed when the versions of the client and the server do not match
+pub const GAME_VERSION: &str = env!("CARGO_PKG_VERSION");
+
+/// Optional features supported by a client or a server, as a bitset
+pub type FeatureFlags = u64;
+
+/// Features supported by this build, no optional feature exists yet
+pub const SUPPORTED_FEATURES: FeatureFlags = 0;
+
+/// First message sent by a client, before any other message\
+/// Its layout must never change, so that every version can read it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct HandshakeRequest {
+    pub protocol_version: u32,
+    pub game_version: String,
+    pub features: FeatureFlags,
+}
+
+impl HandshakeRequest {
+    pub fn current() -> Self {
+        Self {
+            protocol_version: PROTOCOL_VERSION,
+            game_version: GAME_VERSION.to_string(),
+            features: SUPPORTED_FEATURES,
+        }
+    }
+}
+
+/// Answer of the server to a `HandshakeRequest`\
+/// Its layout must never change, so that every version can read it
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub enum HandshakeResponse {
+    Accepted {
+        game_version: String,
+        /// Features supported by both the client and the server
+        features: FeatureFlags,
+    },
+    Rejected {
+        reason: String,
+    },
+}
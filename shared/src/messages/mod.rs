@@ -1,13 +1,16 @@
 mod auth;
 mod chat;
+mod handshake;
 pub mod player;
 mod system;
 mod world;
 
+use crate::players::{Inventory, InventoryAction};
 use crate::world::BlockData;
 pub use auth::*;
-use bevy::math::{IVec3, Vec3};
+use bevy::math::IVec3;
 pub use chat::*;
+pub use handshake::*;
 pub use player::*;
 use serde::{Deserialize, Serialize};
 pub use system::*;
@@ -17,8 +20,10 @@ pub type PlayerId = u64;
 
 #[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
 pub enum ClientToServerMessage {
+    // Must stay the first variant, so that clients of any version are understood
+    Handshake(HandshakeRequest),
     AuthRegisterRequest(AuthRegisterRequest),
-    ChatMessage(ChatMessage),
+    ChatMessage(ChatMessageRequest),
     Exit(ExitOrder),
     PlayerInputs(PlayerInputs),
     WorldUpdateRequest {
@@ -30,17 +35,24 @@ pub enum ClientToServerMessage {
     BlockInteraction {
         position: IVec3,
         block_type: Option<BlockData>,
+        /// Inventory slot the placed block is taken from, ignored when breaking a block
+        slot: u32,
     },
-    SetPlayerPosition {
-        // should be deprecated in the long run
-        position: Vec3,
-    },
+    InventoryAction(InventoryAction),
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub enum ServerToClientMessage {
+    // Must stay the first variant, so that servers of any version are understood
+    Handshake(HandshakeResponse),
     AuthRegisterResponse(AuthRegisterResponse),
-    ChatConversation(ChatConversation),
+    /// Recent messages, sent once to a player joining the game
+    ChatHistory(ChatConversation),
+    ChatMessage(ChatMessage),
     WorldUpdate(WorldUpdate),
+    BlockChanges(BlockChanges),
     PlayerSpawn(PlayerSpawnEvent),
+    PlayerDespawn(PlayerDespawnEvent),
+    /// Whole inventory of the player, sent every time the server changes it
+    InventoryUpdate(Inventory),
 }
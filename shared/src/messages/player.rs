@@ -2,6 +2,7 @@ use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
 use super::PlayerId;
+use crate::players::GameMode;
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub enum NetworkPlayerInput {
@@ -17,9 +18,52 @@ pub enum NetworkPlayerInput {
 
 #[derive(Serialize, Deserialize, PartialEq, Debug, Clone)]
 pub struct PlayerInputs {
+    /// Client frame at which the inputs were captured, strictly increasing
     pub tick: u64,
     pub actions: Vec<NetworkPlayerInput>,
+    /// Direction the camera is looking at
     pub direction: Vec3,
+    /// Duration of the frame, in seconds. The server never moves a player faster than real time
+    pub delta: f32,
+}
+
+/// Movement state of a player computed by the server, once its inputs up to `last_input_tick` are applied
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct PlayerMovementState {
+    pub last_input_tick: u64,
+    pub vertical_velocity: f32,
+    pub on_ground: bool,
+    pub is_flying: bool,
+}
+
+/// Direction a player is looking at, in radians
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy, Default)]
+pub struct PlayerOrientation {
+    pub yaw: f32,
+    pub pitch: f32,
+}
+
+impl PlayerOrientation {
+    pub fn from_direction(direction: Vec3) -> Self {
+        let direction = direction.normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return Self::default();
+        }
+        Self {
+            yaw: f32::atan2(-direction.x, -direction.z),
+            pitch: direction.y.clamp(-1.0, 1.0).asin(),
+        }
+    }
+
+    /// Rotation of the body, which only follows the yaw
+    pub fn body_rotation(&self) -> Quat {
+        Quat::from_rotation_y(self.yaw)
+    }
+
+    /// Rotation of the head, relative to the body
+    pub fn head_rotation(&self) -> Quat {
+        Quat::from_rotation_x(self.pitch)
+    }
 }
 
 #[derive(Event, Serialize, Deserialize, PartialEq, Debug, Clone)]
@@ -27,4 +71,13 @@ pub struct PlayerSpawnEvent {
     pub id: PlayerId,
     pub name: String,
     pub position: Vec3,
+    pub orientation: PlayerOrientation,
+    pub game_mode: GameMode,
+    pub spawn_point: Vec3,
+}
+
+/// Sent when a player leaves the server, its entity must be removed
+#[derive(Event, Serialize, Deserialize, PartialEq, Debug, Clone)]
+pub struct PlayerDespawnEvent {
+    pub id: PlayerId,
 }
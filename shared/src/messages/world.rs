@@ -1,15 +1,35 @@
 use std::collections::HashMap;
 
-use crate::world::ServerChunk;
+use crate::world::{BlockData, ServerChunk};
 use bevy::math::{IVec3, Vec3};
 use serde::{Deserialize, Serialize};
 
-use super::PlayerId;
+use super::{PlayerId, PlayerMovementState, PlayerOrientation};
 
 #[derive(Default, Serialize, Deserialize, Debug, Clone)]
 pub struct WorldUpdate {
     pub tick: u64,
     pub new_map: HashMap<IVec3, ServerChunk>,
     pub player_positions: HashMap<PlayerId, Vec3>,
+    /// Used by the clients to reconcile their predicted movements with `player_positions`
+    pub player_states: HashMap<PlayerId, PlayerMovementState>,
+    pub player_orientations: HashMap<PlayerId, PlayerOrientation>,
+    /// Time elapsed since the start of the server when the update was sent, in seconds\
+    /// Used by the clients to interpolate the movements of the other players
+    pub timestamp: f64,
     pub time: u64,
 }
+
+/// New content of a block, `None` when it was removed
+#[derive(Serialize, Deserialize, PartialEq, Debug, Clone, Copy)]
+pub struct BlockChange {
+    pub position: IVec3,
+    pub block: Option<BlockData>,
+}
+
+/// Blocks modified on the server since the previous batch, in the order they were modified
+#[derive(Default, Serialize, Deserialize, Debug, Clone)]
+pub struct BlockChanges {
+    pub tick: u64,
+    pub changes: Vec<BlockChange>,
+}
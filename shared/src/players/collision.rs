@@ -0,0 +1,61 @@
+use bevy::prelude::*;
+
+use crate::world::WorldMap;
+
+use super::Player;
+
+fn is_block_at_position(position: Vec3, world_map: &impl WorldMap) -> bool {
+    if let Some(block) = world_map.get_block_by_coordinates(&IVec3::new(
+        position.x.floor() as i32,
+        position.y.floor() as i32,
+        position.z.floor() as i32,
+    )) {
+        block.id.has_hitbox()
+    } else {
+        false
+    }
+}
+
+pub fn check_player_collision(
+    player_position: Vec3,
+    player: &Player,
+    world_map: &impl WorldMap,
+) -> bool {
+    // Vérification de la collision avec les pieds et la tête du joueur
+    let foot_position = Vec3::new(
+        player_position.x,
+        player_position.y - player.height / 2.0,
+        player_position.z,
+    );
+    let head_position = Vec3::new(
+        player_position.x,
+        player_position.y + player.height / 2.0,
+        player_position.z,
+    );
+
+    // On vérifie les coins du joueur
+    let offsets = [
+        Vec3::new(-player.width / 2.0, 0.0, -player.width / 2.0), // bas gauche devant
+        Vec3::new(player.width / 2.0, 0.0, -player.width / 2.0),  // bas droite devant
+        Vec3::new(-player.width / 2.0, 0.0, player.width / 2.0),  // bas gauche derrière
+        Vec3::new(player.width / 2.0, 0.0, player.width / 2.0),   // bas droite derrière
+    ];
+
+    // Vérifier la collision au niveau des pieds
+    for offset in &offsets {
+        let check_pos = foot_position + *offset;
+        if is_block_at_position(check_pos, world_map) {
+            return true;
+        }
+    }
+
+    // Vérifier la collision au niveau de la tête
+    for offset in &offsets {
+        let check_pos = head_position + *offset;
+        if is_block_at_position(check_pos, world_map) {
+            return true;
+        }
+    }
+
+    false
+}
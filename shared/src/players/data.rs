@@ -0,0 +1,79 @@
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use super::SPAWN_POSITION;
+use crate::messages::{PlayerId, PlayerMovementState, PlayerOrientation};
+
+/// Maximum distance between a player and the blocks it can break or place
+pub const INTERACTION_DISTANCE: f32 = 7.;
+
+#[derive(Serialize, Deserialize, PartialEq, Eq, Debug, Clone, Copy, Default)]
+pub enum GameMode {
+    Survival,
+    #[default]
+    Creative,
+}
+
+impl GameMode {
+    pub fn can_fly(&self) -> bool {
+        *self == GameMode::Creative
+    }
+}
+
+#[derive(Component, Clone, Debug)]
+pub struct Player {
+    pub id: PlayerId,
+    pub name: String,
+    pub vertical_velocity: f32,
+    pub on_ground: bool,
+    // pub view_mode: ViewMode,
+    // pub is_chunk_debug_mode_enabled: bool,
+    pub is_flying: bool,
+    // pub inventory: HashMap<RegistryId, items::Item>,
+    pub height: f32,
+    pub width: f32,
+    pub orientation: PlayerOrientation,
+    pub game_mode: GameMode,
+    /// Where the player comes back after falling below the world
+    pub spawn_point: Vec3,
+}
+
+impl Player {
+    pub fn new(id: PlayerId, name: String) -> Self {
+        Self {
+            id,
+            name,
+            vertical_velocity: 0.0,
+            on_ground: true,
+            is_flying: false,
+            height: 1.8,
+            width: 0.8,
+            orientation: PlayerOrientation::default(),
+            game_mode: GameMode::default(),
+            spawn_point: SPAWN_POSITION,
+        }
+    }
+
+    pub fn movement_state(&self, last_input_tick: u64) -> PlayerMovementState {
+        PlayerMovementState {
+            last_input_tick,
+            vertical_velocity: self.vertical_velocity,
+            on_ground: self.on_ground,
+            is_flying: self.is_flying,
+        }
+    }
+
+    pub fn apply_movement_state(&mut self, state: &PlayerMovementState) {
+        self.vertical_velocity = state.vertical_velocity;
+        self.on_ground = state.on_ground;
+        self.is_flying = state.is_flying;
+    }
+
+    pub fn toggle_fly_mode(&mut self) {
+        if !self.is_flying && !self.game_mode.can_fly() {
+            return;
+        }
+        self.is_flying = !self.is_flying;
+        self.vertical_velocity = 0.0; // Réinitialisation de la vélocité
+    }
+}
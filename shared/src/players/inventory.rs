@@ -0,0 +1,202 @@
+use std::collections::HashMap;
+
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::world::{BlockId, ItemId, ItemStack, ItemType};
+
+pub const MAX_INVENTORY_SLOTS: u32 = 4 * 9;
+
+/// Items of a player, owned by the server\
+/// The client only keeps a copy of it, replaced every time the server sends an update
+#[derive(Resource, Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct Inventory {
+    pub inner: HashMap<u32, ItemStack>,
+}
+
+/// Changes of the inventory requested by a client, checked and applied by the server
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InventoryAction {
+    /// Moves `nb` items to a slot which is empty or holds the same item
+    Move { from: u32, to: u32, nb: u32 },
+    /// Moves half of a stack, rounded up, to a slot which is empty or holds the same item
+    Split { from: u32, to: u32 },
+    /// Exchanges the content of two slots
+    Swap { first: u32, second: u32 },
+    /// Throws `nb` items of a slot away
+    Drop { slot: u32, nb: u32 },
+}
+
+impl Inventory {
+    pub fn new() -> Self {
+        Self {
+            inner: HashMap::new(),
+        }
+    }
+
+    // Ajoute un item à l'inventaire du joueur
+    /// Returns the number of items which did not fit in the inventory
+    pub fn add_item_to_inventory(&mut self, mut stack: ItemStack) -> u32 {
+        let max_stack = stack.item_id.get_max_stack();
+        for i in 0..MAX_INVENTORY_SLOTS {
+            let mut existing_nb = 0;
+            if let Some(existing_item) = self.inner.get(&i) {
+                // If not item of right type or stack already full : pass
+                if existing_item.item_id != stack.item_id || existing_item.nb >= max_stack {
+                    continue;
+                }
+
+                existing_nb = existing_item.nb;
+            }
+
+            // Only fill the room left in the slot, so that the count never overflows
+            let added = stack.nb.min(max_stack - existing_nb);
+            let inserted_stack = ItemStack {
+                item_id: stack.item_id,
+                item_type: stack.item_type,
+                nb: existing_nb + added,
+            };
+            stack.nb -= added;
+
+            // Push inserted items in right inventory slot
+            self.inner.insert(i, inserted_stack);
+
+            // If no more items to add, end loop
+            if stack.nb == 0 {
+                break;
+            }
+        }
+
+        stack.nb
+    }
+
+    /// Add items to stack at specified position\
+    /// Stacks cannot exceed MAX_ITEM_STACK number of items\
+    /// Returns number of items really added to the stack
+    pub fn add_item_to_stack(
+        &mut self,
+        stack: u32,
+        mut nb: u32,
+        id: ItemId,
+        item_type: ItemType,
+    ) -> u32 {
+        let item_option = self.inner.get(&stack);
+        let mut new_item = ItemStack {
+            item_id: id,
+            nb,
+            item_type,
+        };
+
+        if let Some(item) = item_option {
+            nb = nb.min(item.item_id.get_max_stack().saturating_sub(item.nb));
+            new_item.nb = nb + item.nb;
+        }
+        self.inner.insert(stack, new_item);
+        nb
+    }
+
+    /// Removes items from stack at specified position\
+    /// Stacks cannot have < 0 number of items\
+    /// Returns number of items really removed from the stack
+    pub fn remove_item_from_stack(&mut self, stack: u32, mut nb: u32) -> u32 {
+        let item_option = self.inner.get(&stack);
+
+        if let Some(&item) = item_option {
+            if nb >= item.nb {
+                nb = item.nb;
+                self.inner.remove(&stack);
+            } else {
+                self.inner.insert(
+                    stack,
+                    ItemStack {
+                        item_id: item.item_id,
+                        nb: item.nb - nb,
+                        item_type: item.item_type,
+                    },
+                );
+            }
+            return nb;
+        }
+        0
+    }
+
+    /// Removes one block from the slot, used to place it in the world\
+    /// Returns false if the slot does not hold this block
+    pub fn take_block(&mut self, slot: u32, block_id: BlockId) -> bool {
+        match self.inner.get(&slot) {
+            Some(stack) if stack.item_type == ItemType::Block(block_id) => {
+                self.remove_item_from_stack(slot, 1);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Applies an action of the player, the inventory is left untouched if it is not valid
+    pub fn apply(&mut self, action: InventoryAction) -> Result<(), String> {
+        match action {
+            InventoryAction::Move { from, to, nb } => self.move_items(from, to, nb),
+            InventoryAction::Split { from, to } => {
+                let nb = self
+                    .inner
+                    .get(&from)
+                    .map_or(0, |stack| stack.nb.div_ceil(2));
+                self.move_items(from, to, nb)
+            }
+            InventoryAction::Swap { first, second } => {
+                check_slot(first)?;
+                check_slot(second)?;
+                let first_stack = self.inner.remove(&first);
+                let second_stack = self.inner.remove(&second);
+                if let Some(stack) = first_stack {
+                    self.inner.insert(second, stack);
+                }
+                if let Some(stack) = second_stack {
+                    self.inner.insert(first, stack);
+                }
+                Ok(())
+            }
+            InventoryAction::Drop { slot, nb } => {
+                check_slot(slot)?;
+                if self.remove_item_from_stack(slot, nb) == 0 {
+                    return Err(format!("slot {} is empty", slot));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn move_items(&mut self, from: u32, to: u32, nb: u32) -> Result<(), String> {
+        check_slot(from)?;
+        check_slot(to)?;
+        if from == to {
+            return Ok(());
+        }
+
+        let Some(&stack) = self.inner.get(&from) else {
+            return Err(format!("slot {} is empty", from));
+        };
+        if let Some(target) = self.inner.get(&to) {
+            if target.item_id != stack.item_id {
+                return Err(format!("slots {} and {} hold different items", from, to));
+            }
+        }
+
+        let nb = nb.min(stack.nb);
+        if nb == 0 {
+            return Ok(());
+        }
+
+        // The target stack may not have room for all of them
+        let moved = self.add_item_to_stack(to, nb, stack.item_id, stack.item_type);
+        self.remove_item_from_stack(from, moved);
+        Ok(())
+    }
+}
+
+fn check_slot(slot: u32) -> Result<(), String> {
+    if slot >= MAX_INVENTORY_SLOTS {
+        return Err(format!("slot {} does not exist", slot));
+    }
+    Ok(())
+}
@@ -0,0 +1,9 @@
+pub mod collision;
+pub mod data;
+pub mod inventory;
+pub mod movement;
+
+pub use collision::*;
+pub use data::*;
+pub use inventory::*;
+pub use movement::*;
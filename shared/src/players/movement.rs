@@ -0,0 +1,118 @@
+use bevy::prelude::*;
+
+use crate::messages::{NetworkPlayerInput, PlayerInputs};
+use crate::world::WorldMap;
+
+use super::{check_player_collision, Player};
+
+pub const GRAVITY: f32 = -9.8 * 4.0;
+pub const WALK_SPEED: f32 = 5.0;
+pub const FLY_SPEED: f32 = 15.0;
+pub const JUMP_VELOCITY: f32 = 10.0;
+
+/// Players falling below this height are sent back to their spawn point
+pub const FALL_LIMIT: f32 = -50.0;
+/// Spawn point of a player, until the server sends the one of its world
+pub const SPAWN_POSITION: Vec3 = Vec3::new(0.0, 100.0, 0.0);
+
+/// Moves a player according to a set of inputs held during `delta` seconds\
+/// Runs on the server, which owns the positions, and on the client to predict them
+pub fn simulate_player_movement(
+    player: &mut Player,
+    position: &mut Vec3,
+    world_map: &impl WorldMap,
+    inputs: &PlayerInputs,
+    delta: f32,
+) {
+    let is_pressed = |action: NetworkPlayerInput| inputs.actions.contains(&action);
+
+    if is_pressed(NetworkPlayerInput::ToggleFlyMode) {
+        player.toggle_fly_mode();
+    }
+
+    let speed = if player.is_flying {
+        FLY_SPEED
+    } else {
+        WALK_SPEED
+    };
+
+    // flying mode
+    if player.is_flying {
+        if is_pressed(NetworkPlayerInput::FlyUp) {
+            position.y += speed * 2.0 * delta;
+        }
+        if is_pressed(NetworkPlayerInput::FlyDown) {
+            position.y -= speed * 2.0 * delta;
+        }
+    }
+
+    // Calculate movement directions relative to the camera
+    let forward = Vec3::new(inputs.direction.x, 0.0, inputs.direction.z).normalize_or_zero();
+    let right = forward.cross(Vec3::Y);
+
+    let mut direction = Vec3::ZERO;
+
+    if is_pressed(NetworkPlayerInput::Backward) {
+        direction -= forward;
+    }
+    if is_pressed(NetworkPlayerInput::Forward) {
+        direction += forward;
+    }
+    if is_pressed(NetworkPlayerInput::Left) {
+        direction -= right;
+    }
+    if is_pressed(NetworkPlayerInput::Right) {
+        direction += right;
+    }
+
+    // Move the player (xy plane only), only if there are no blocks
+    if direction.length_squared() > 0.0 {
+        direction = direction.normalize();
+
+        // Déplacement sur l'axe X
+        let new_pos_x = *position + Vec3::new(direction.x, 0.0, 0.0) * speed * delta;
+
+        if player.is_flying || !check_player_collision(new_pos_x, player, world_map) {
+            position.x = new_pos_x.x;
+        }
+
+        // Déplacement sur l'axe Z
+        let new_pos_z = *position + Vec3::new(0.0, 0.0, direction.z) * speed * delta;
+
+        if player.is_flying || !check_player_collision(new_pos_z, player, world_map) {
+            position.z = new_pos_z.z;
+        }
+    }
+
+    // Handle jumping (if on the ground) and gravity, only if not flying
+    if !player.is_flying {
+        if player.on_ground && is_pressed(NetworkPlayerInput::Jump) {
+            // Player can jump only when grounded
+            player.vertical_velocity = JUMP_VELOCITY;
+            player.on_ground = false;
+        } else if !player.on_ground {
+            // Apply gravity when the player is in the air
+            player.vertical_velocity += GRAVITY * delta;
+        }
+    }
+
+    // apply gravity and verify vertical collisions
+    let new_y = position.y + player.vertical_velocity * delta;
+
+    // Vérifier uniquement les collisions verticales (sol et plafond)
+    if check_player_collision(Vec3::new(position.x, new_y, position.z), player, world_map) {
+        // Si un bloc est détecté sous le joueur, il reste sur le bloc
+        player.on_ground = true;
+        player.vertical_velocity = 0.0; // Réinitialiser la vélocité verticale si le joueur est au sol
+    } else {
+        // Si aucun bloc n'est détecté sous le joueur, il continue de tomber
+        position.y = new_y;
+        player.on_ground = false;
+    }
+
+    // If the player is below the world, reset their position
+    if position.y < FALL_LIMIT {
+        *position = player.spawn_point;
+        player.vertical_velocity = 0.0;
+    }
+}
@@ -37,6 +37,12 @@ pub enum BlockId {
     Snow,
     SpruceLeaves,
     SpruceLog,
+    CoalOre,
+    IronOre,
+    GoldOre,
+    DiamondOre,
+    Water,
+    Lava,
 }
 
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
@@ -47,12 +53,22 @@ pub enum BlockDirection {
     Left,
 }
 
+/// Level of a liquid source block
+pub const LIQUID_SOURCE_LEVEL: u8 = 0;
+/// Lowest level of a liquid flowing horizontally
+pub const LIQUID_MAX_FLOW_LEVEL: u8 = 7;
+/// Level of a liquid falling from the block above, always rendered at full height
+pub const LIQUID_FALLING_LEVEL: u8 = 8;
+
 /// Data associated with a given `BlockId`
 #[derive(Debug, Copy, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct BlockData {
     pub id: BlockId,
     pub flipped: bool,
     pub direction: BlockDirection,
+    /// Liquid level, see `LIQUID_SOURCE_LEVEL`. Always 0 for other blocks
+    #[serde(default)]
+    pub level: u8,
 }
 
 impl BlockData {
@@ -61,6 +77,23 @@ impl BlockData {
             id,
             flipped,
             direction,
+            level: LIQUID_SOURCE_LEVEL,
+        }
+    }
+
+    /// Creates a liquid block with the given level
+    pub fn liquid(id: BlockId, level: u8) -> Self {
+        BlockData {
+            level,
+            ..Self::new(id, false, BlockDirection::Front)
+        }
+    }
+
+    /// Height of the surface of a liquid block, between 0 and 1
+    pub fn liquid_height(&self) -> f32 {
+        match self.level {
+            LIQUID_FALLING_LEVEL => 1.,
+            level => (8 - level.min(LIQUID_MAX_FLOW_LEVEL)) as f32 / 9.,
         }
     }
 }
@@ -81,7 +114,7 @@ pub enum BlockTransparency {
 impl BlockId {
     pub fn has_hitbox(&self) -> bool {
         match *self {
-            BlockId::Dandelion | BlockId::Poppy => false,
+            BlockId::Dandelion | BlockId::Poppy | BlockId::Water | BlockId::Lava => false,
             _ => true,
         }
     }
@@ -92,7 +125,8 @@ impl BlockId {
 
     pub fn get_break_time(&self) -> f32 {
         match *self {
-            Self::Bedrock => -1.,
+            Self::Bedrock | Self::Water | Self::Lava => -1.,
+            Self::GoldOre | Self::DiamondOre => 7.,
             _ => 5.,
         }
     }
@@ -148,21 +182,39 @@ impl BlockId {
             BlockId::Poppy => vec![(1, ItemId::Dandelion, 1)],
             BlockId::SpruceLog => vec![(1, ItemId::SpruceLog, 1)],
             BlockId::Snow => vec![(1, ItemId::Snowball, 4)],
+            BlockId::CoalOre => vec![(1, ItemId::Coal, 1)],
+            BlockId::IronOre => vec![(1, ItemId::IronOre, 1)],
+            BlockId::GoldOre => vec![(1, ItemId::GoldOre, 1)],
+            BlockId::DiamondOre => vec![(1, ItemId::Diamond, 1)],
             _ => vec![],
         }
     }
 
     pub fn get_tags(&self) -> Vec<BlockTags> {
         match *self {
-            BlockId::Stone => vec![BlockTags::Stone, BlockTags::Solid],
+            BlockId::Stone
+            | BlockId::CoalOre
+            | BlockId::IronOre
+            | BlockId::GoldOre
+            | BlockId::DiamondOre => vec![BlockTags::Stone, BlockTags::Solid],
             _ => vec![BlockTags::Solid],
         }
     }
 
+    pub fn is_liquid(&self) -> bool {
+        self.get_visibility() == BlockTransparency::Liquid
+    }
+
+    /// Whether a placed block can take the place of this one
+    pub fn is_replaceable(&self) -> bool {
+        self.is_liquid()
+    }
+
     pub fn get_visibility(&self) -> BlockTransparency {
         match *self {
             Self::Dandelion | Self::Poppy => BlockTransparency::Decoration,
             Self::Glass | Self::OakLeaves | Self::SpruceLeaves => BlockTransparency::Transparent,
+            Self::Water | Self::Lava => BlockTransparency::Liquid,
             _ => BlockTransparency::Solid,
         }
     }
@@ -1,4 +1,4 @@
-use crate::messages::PlayerId;
+use crate::messages::{BlockChange, PlayerId};
 use crate::world::block_to_chunk_coord;
 use crate::world::global_block_to_chunk_pos;
 use crate::world::to_local_pos;
@@ -12,12 +12,13 @@ use std::collections::HashMap;
 use std::fmt::Debug;
 
 use super::BlockData;
+use super::ChunkStorage;
 use super::ItemId;
 use super::ItemType;
 
 #[derive(Clone, Default, Serialize, Deserialize, Debug)]
 pub struct ServerChunk {
-    pub map: HashMap<IVec3, BlockData>,
+    pub blocks: ChunkStorage,
     /// Timestamp marking the last update this chunk has received
     pub ts: u64,
 }
@@ -26,7 +27,8 @@ pub struct ServerChunk {
 pub struct ServerWorldMap {
     pub name: String,
     pub map: HashMap<IVec3, ServerChunk>,
-    pub chunks_to_update: Vec<IVec3>,
+    /// Blocks modified since the last broadcast, sent to the clients as `BlockChanges`
+    pub block_changes: Vec<BlockChange>,
     pub player_positions: HashMap<PlayerId, Vec3>,
     pub time: u64,
 }
@@ -34,7 +36,7 @@ pub struct ServerWorldMap {
 #[derive(Resource, Clone, Serialize, Deserialize)]
 pub struct WorldSeed(pub u32);
 
-#[derive(Debug, Clone, Serialize, Deserialize, Copy)]
+#[derive(Debug, Clone, Serialize, Deserialize, Copy, PartialEq)]
 pub struct ItemStack {
     pub item_id: ItemId,
     pub item_type: ItemType,
@@ -58,6 +60,12 @@ pub struct Biome {
     pub height_variation: i32,
     pub surface_block: BlockId,
     pub sub_surface_block: BlockId,
+    /// Vertical displacement of the terrain by 3D noise, in blocks (overhangs)
+    pub overhang_amplitude: f64,
+    /// Higher values give wider and more frequent caves, 0 disables them
+    pub cave_threshold: f64,
+    /// Maximum depth of ravines below the surface, 0 disables them
+    pub ravine_depth: i32,
 }
 
 pub fn get_biome_data(biome_type: BiomeType) -> Biome {
@@ -68,6 +76,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            overhang_amplitude: 2.0,
+            cave_threshold: 0.01,
+            ravine_depth: 20,
         },
         BiomeType::Forest => Biome {
             biome_type: BiomeType::Forest,
@@ -75,6 +86,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 2,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            overhang_amplitude: 3.0,
+            cave_threshold: 0.01,
+            ravine_depth: 16,
         },
         BiomeType::MediumMountain => Biome {
             biome_type: BiomeType::MediumMountain,
@@ -82,6 +96,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 4,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            overhang_amplitude: 6.0,
+            cave_threshold: 0.012,
+            ravine_depth: 30,
         },
         BiomeType::HighMountain => Biome {
             biome_type: BiomeType::HighMountain,
@@ -89,6 +106,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 7,
             surface_block: BlockId::Grass,
             sub_surface_block: BlockId::Dirt,
+            overhang_amplitude: 10.0,
+            cave_threshold: 0.014,
+            ravine_depth: 40,
         },
         BiomeType::Desert => Biome {
             biome_type: BiomeType::Desert,
@@ -96,6 +116,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Sand,
             sub_surface_block: BlockId::Sand,
+            overhang_amplitude: 1.5,
+            cave_threshold: 0.008,
+            ravine_depth: 12,
         },
         BiomeType::IcePlain => Biome {
             biome_type: BiomeType::IcePlain,
@@ -103,6 +126,9 @@ pub fn get_biome_data(biome_type: BiomeType) -> Biome {
             height_variation: 1,
             surface_block: BlockId::Snow,
             sub_surface_block: BlockId::Ice,
+            overhang_amplitude: 1.0,
+            cave_threshold: 0.008,
+            ravine_depth: 12,
         },
     }
 }
@@ -121,7 +147,7 @@ impl ServerWorldMap {
                 let sub_x: i32 = ((x % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
                 let sub_y: i32 = ((y % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
                 let sub_z: i32 = ((z % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
-                chunk.map.get(&IVec3::new(sub_x, sub_y, sub_z))
+                chunk.blocks.get(&IVec3::new(sub_x, sub_y, sub_z))
             }
             None => None,
         }
@@ -132,9 +158,6 @@ impl ServerWorldMap {
         let kind: BlockData = *block;
 
         let chunk_pos: IVec3 = global_block_to_chunk_pos(global_block_pos);
-        let cx = chunk_pos.x;
-        let cy = chunk_pos.y;
-        let cz = chunk_pos.z;
 
         let chunk_map: &mut ServerChunk =
             self.map
@@ -142,8 +165,11 @@ impl ServerWorldMap {
 
         let local_block_pos: IVec3 = to_local_pos(global_block_pos);
 
-        chunk_map.map.remove(&local_block_pos);
-        self.chunks_to_update.push(IVec3::new(cx, cy, cz));
+        chunk_map.blocks.remove(&local_block_pos);
+        self.block_changes.push(BlockChange {
+            position: *global_block_pos,
+            block: None,
+        });
 
         Some(kind)
     }
@@ -160,8 +186,23 @@ impl ServerWorldMap {
         let sub_y: i32 = ((y % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
         let sub_z: i32 = ((z % CHUNK_SIZE) + CHUNK_SIZE) % CHUNK_SIZE;
 
-        chunk.map.insert(IVec3::new(sub_x, sub_y, sub_z), block);
-        self.chunks_to_update.push(IVec3::new(cx, cy, cz));
+        chunk.blocks.insert(IVec3::new(sub_x, sub_y, sub_z), block);
+        self.block_changes.push(BlockChange {
+            position: *position,
+            block: Some(block),
+        });
+    }
+}
+
+/// Read access to the blocks of a world, implemented by both the server and client maps\
+/// Used by the logic shared between the client and the server, such as player physics
+pub trait WorldMap {
+    fn get_block_by_coordinates(&self, position: &IVec3) -> Option<&BlockData>;
+}
+
+impl WorldMap for ServerWorldMap {
+    fn get_block_by_coordinates(&self, position: &IVec3) -> Option<&BlockData> {
+        ServerWorldMap::get_block_by_coordinates(self, position)
     }
 }
 
@@ -36,6 +36,10 @@ pub enum ItemId {
     Snow,
     Snowball,
     SpruceLog,
+    Coal,
+    IronOre,
+    GoldOre,
+    Diamond,
 }
 
 impl ItemId {
@@ -61,8 +65,10 @@ impl ItemId {
             Self::Cobblestone => ItemType::Block(BlockId::Cobblestone),
             Self::Snow => ItemType::Block(BlockId::Snow),
             Self::SpruceLog => ItemType::Block(BlockId::SpruceLog),
+            Self::IronOre => ItemType::Block(BlockId::IronOre),
+            Self::GoldOre => ItemType::Block(BlockId::GoldOre),
 
-            Self::Snowball => ItemType::Generic,
+            Self::Snowball | Self::Coal | Self::Diamond => ItemType::Generic,
         }
     }
 }
@@ -1,9 +1,11 @@
 pub mod blocks;
 pub mod data;
 pub mod items;
+pub mod storage;
 mod utils;
 
 pub use blocks::*;
 pub use data::*;
 pub use items::*;
+pub use storage::*;
 pub use utils::*;
@@ -0,0 +1,446 @@
+use bevy::math::IVec3;
+use serde::de::Error;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::BlockData;
+use crate::CHUNK_SIZE;
+
+/// Number of blocks in a chunk
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// Dense storage of the blocks of a 16x16x16 chunk
+///
+/// Every distinct block state of the chunk is stored once in a palette, each block
+/// being an index in this palette packed on the smallest number of bits possible.\
+/// Uniform chunks (only air, only stone...) use a single palette entry and no index data.
+///
+/// Blocks are indexed by local position, in the `0..CHUNK_SIZE` range on every axis.
+#[derive(Clone, Debug)]
+pub struct ChunkStorage {
+    /// Block states used in the chunk, `None` being air
+    palette: Vec<Option<BlockData>>,
+    /// Number of blocks using each palette entry, an entry at 0 can be reused
+    counts: Vec<u16>,
+    /// Number of bits used by each index, 0 when the chunk is uniform
+    bits: u8,
+    /// Palette indices, packed without crossing `u64` boundaries
+    data: Vec<u64>,
+    /// Number of non-air blocks
+    block_count: u16,
+}
+
+impl Default for ChunkStorage {
+    fn default() -> Self {
+        Self {
+            palette: vec![None],
+            counts: vec![CHUNK_VOLUME as u16],
+            bits: 0,
+            data: Vec::new(),
+            block_count: 0,
+        }
+    }
+}
+
+impl PartialEq for ChunkStorage {
+    fn eq(&self, other: &Self) -> bool {
+        self.block_count == other.block_count && self.iter().eq(other.iter())
+    }
+}
+
+/// Whether this local position is inside the bounds of a chunk
+#[inline]
+pub fn is_in_chunk(local_pos: &IVec3) -> bool {
+    local_pos.cmpge(IVec3::ZERO).all() && local_pos.cmplt(IVec3::splat(CHUNK_SIZE)).all()
+}
+
+#[inline]
+fn block_index(local_pos: &IVec3) -> Option<usize> {
+    if !is_in_chunk(local_pos) {
+        return None;
+    }
+    Some((local_pos.x + local_pos.z * CHUNK_SIZE + local_pos.y * CHUNK_SIZE * CHUNK_SIZE) as usize)
+}
+
+#[inline]
+fn local_pos_from_index(index: usize) -> IVec3 {
+    let index = index as i32;
+    IVec3::new(
+        index % CHUNK_SIZE,
+        index / (CHUNK_SIZE * CHUNK_SIZE),
+        (index / CHUNK_SIZE) % CHUNK_SIZE,
+    )
+}
+
+/// Number of `u64` needed to store every index of a chunk
+fn data_len(bits: u8) -> usize {
+    if bits == 0 {
+        return 0;
+    }
+    let per_word = 64 / bits as usize;
+    CHUNK_VOLUME.div_ceil(per_word)
+}
+
+/// Smallest number of bits able to index a palette of this size
+fn bits_for_palette(len: usize) -> u8 {
+    if len <= 1 {
+        0
+    } else {
+        (usize::BITS - (len - 1).leading_zeros()) as u8
+    }
+}
+
+impl ChunkStorage {
+    /// Creates a chunk filled with a single block state
+    pub fn filled(block: Option<BlockData>) -> Self {
+        Self {
+            palette: vec![block],
+            counts: vec![CHUNK_VOLUME as u16],
+            bits: 0,
+            data: Vec::new(),
+            block_count: if block.is_some() {
+                CHUNK_VOLUME as u16
+            } else {
+                0
+            },
+        }
+    }
+
+    #[inline]
+    fn palette_index(&self, index: usize) -> usize {
+        if self.bits == 0 {
+            return 0;
+        }
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) * self.bits as usize;
+        let mask = (1u64 << self.bits) - 1;
+        ((self.data[index / per_word] >> shift) & mask) as usize
+    }
+
+    #[inline]
+    fn set_palette_index(&mut self, index: usize, value: usize) {
+        let per_word = 64 / self.bits as usize;
+        let shift = (index % per_word) * self.bits as usize;
+        let mask = (1u64 << self.bits) - 1;
+        let word = &mut self.data[index / per_word];
+        *word = (*word & !(mask << shift)) | ((value as u64 & mask) << shift);
+    }
+
+    /// Repacks every index on a new number of bits
+    fn resize(&mut self, bits: u8) {
+        let indices: Vec<usize> = (0..CHUNK_VOLUME).map(|i| self.palette_index(i)).collect();
+        self.bits = bits;
+        self.data = vec![0; data_len(bits)];
+        for (i, value) in indices.into_iter().enumerate() {
+            self.set_palette_index(i, value);
+        }
+    }
+
+    /// Returns the palette entry of this block state, adding it if needed
+    fn get_or_insert_palette_entry(&mut self, block: Option<BlockData>) -> usize {
+        if let Some(entry) = self.palette.iter().position(|b| *b == block) {
+            return entry;
+        }
+
+        // Reuse an entry no longer used by any block
+        if let Some(entry) = self.counts.iter().position(|count| *count == 0) {
+            self.palette[entry] = block;
+            return entry;
+        }
+
+        self.palette.push(block);
+        self.counts.push(0);
+        let bits = bits_for_palette(self.palette.len());
+        if bits > self.bits {
+            self.resize(bits);
+        }
+        self.palette.len() - 1
+    }
+
+    /// Returns the block at this local position, `None` for air
+    #[inline]
+    pub fn get(&self, local_pos: &IVec3) -> Option<&BlockData> {
+        let index = block_index(local_pos)?;
+        self.palette[self.palette_index(index)].as_ref()
+    }
+
+    /// Sets the block at this local position, `None` to place air\
+    /// Returns the block previously there
+    pub fn set(&mut self, local_pos: &IVec3, block: Option<BlockData>) -> Option<BlockData> {
+        let index = block_index(local_pos)?;
+        let old_entry = self.palette_index(index);
+        let old_block = self.palette[old_entry];
+        if old_block == block {
+            return old_block;
+        }
+
+        // The chunk can no longer be uniform, so the indices always use at least one bit here
+        let entry = self.get_or_insert_palette_entry(block);
+        self.set_palette_index(index, entry);
+        self.counts[old_entry] -= 1;
+        self.counts[entry] += 1;
+
+        if old_block.is_some() {
+            self.block_count -= 1;
+        }
+        if block.is_some() {
+            self.block_count += 1;
+        }
+
+        if self.counts[entry] as usize == CHUNK_VOLUME {
+            // Whole chunk is made of the same block, drop the index data
+            *self = Self::filled(block);
+        }
+
+        old_block
+    }
+
+    /// Places a block at this local position, returns the block previously there
+    pub fn insert(&mut self, local_pos: IVec3, block: BlockData) -> Option<BlockData> {
+        self.set(&local_pos, Some(block))
+    }
+
+    /// Replaces the block at this local position by air, returns the removed block
+    pub fn remove(&mut self, local_pos: &IVec3) -> Option<BlockData> {
+        self.set(local_pos, None)
+    }
+
+    pub fn contains_key(&self, local_pos: &IVec3) -> bool {
+        self.get(local_pos).is_some()
+    }
+
+    /// Number of non-air blocks
+    pub fn len(&self) -> usize {
+        self.block_count as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.block_count == 0
+    }
+
+    /// Iterates over every non-air block with its local position, in memory order
+    pub fn iter(&self) -> impl Iterator<Item = (IVec3, &BlockData)> + '_ {
+        let count = if self.is_empty() { 0 } else { CHUNK_VOLUME };
+        (0..count).filter_map(move |index| {
+            self.palette[self.palette_index(index)]
+                .as_ref()
+                .map(|block| (local_pos_from_index(index), block))
+        })
+    }
+}
+
+/// Network and disk representation of a `ChunkStorage`
+#[derive(Serialize)]
+struct PackedChunkRef<'a> {
+    palette: &'a [Option<BlockData>],
+    bits: u8,
+    data: &'a [u64],
+}
+
+#[derive(Deserialize)]
+struct PackedChunk {
+    palette: Vec<Option<BlockData>>,
+    bits: u8,
+    data: Vec<u64>,
+}
+
+impl Serialize for ChunkStorage {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        PackedChunkRef {
+            palette: &self.palette,
+            bits: self.bits,
+            data: &self.data,
+        }
+        .serialize(serializer)
+    }
+}
+
+impl<'de> Deserialize<'de> for ChunkStorage {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let packed = PackedChunk::deserialize(deserializer)?;
+
+        if packed.palette.is_empty()
+            || packed.bits > 16
+            || packed.bits < bits_for_palette(packed.palette.len())
+            || packed.data.len() != data_len(packed.bits)
+        {
+            return Err(D::Error::custom("invalid chunk storage"));
+        }
+
+        let mut storage = ChunkStorage {
+            counts: vec![0; packed.palette.len()],
+            palette: packed.palette,
+            bits: packed.bits,
+            data: packed.data,
+            block_count: 0,
+        };
+
+        // Counts are not transmitted, rebuild them from the indices
+        for index in 0..CHUNK_VOLUME {
+            let entry = storage.palette_index(index);
+            if entry >= storage.palette.len() {
+                return Err(D::Error::custom("chunk storage index out of palette"));
+            }
+            storage.counts[entry] += 1;
+            if storage.palette[entry].is_some() {
+                storage.block_count += 1;
+            }
+        }
+
+        Ok(storage)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::world::{BlockDirection, BlockId};
+
+    /// Distinct block states, to fill the palette
+    fn block(n: u8) -> BlockData {
+        BlockData::liquid(BlockId::Water, n)
+    }
+
+    fn local_pos(index: usize) -> IVec3 {
+        local_pos_from_index(index)
+    }
+
+    #[test]
+    fn set_and_get_across_resizes() {
+        let mut storage = ChunkStorage::default();
+        assert_eq!(storage.bits, 0);
+
+        // Up to 256 block states and air, indices grow from 1 to 9 bits
+        for n in 0..=255u8 {
+            storage.set(&local_pos(n as usize), Some(block(n)));
+            assert_eq!(storage.bits, bits_for_palette(n as usize + 2));
+            assert_eq!(storage.data.len(), data_len(storage.bits));
+
+            for previous in 0..=n {
+                assert_eq!(
+                    storage.get(&local_pos(previous as usize)),
+                    Some(&block(previous))
+                );
+            }
+            assert_eq!(storage.get(&local_pos(n as usize + 1)), None);
+        }
+        assert_eq!(storage.bits, 9);
+        assert_eq!(storage.len(), 256);
+
+        // Out of the chunk
+        assert_eq!(storage.get(&IVec3::new(CHUNK_SIZE, 0, 0)), None);
+        assert_eq!(storage.set(&IVec3::new(-1, 0, 0), Some(block(0))), None);
+    }
+
+    #[test]
+    fn unused_palette_entries_are_reused() {
+        let mut storage = ChunkStorage::default();
+        storage.set(&local_pos(0), Some(block(1)));
+        storage.set(&local_pos(0), Some(block(2)));
+        // The entry of block(1) is no longer used, block(3) takes it
+        storage.set(&local_pos(0), Some(block(3)));
+        assert_eq!(storage.palette.len(), 3);
+        assert_eq!(storage.bits, 2);
+        assert_eq!(storage.get(&local_pos(0)), Some(&block(3)));
+    }
+
+    #[test]
+    fn collapses_back_to_uniform() {
+        let stone = BlockData::new(BlockId::Stone, false, BlockDirection::Front);
+        let mut storage = ChunkStorage::default();
+        storage.set(&local_pos(0), Some(block(0)));
+
+        for index in 0..CHUNK_VOLUME {
+            storage.set(&local_pos(index), Some(stone));
+        }
+        assert_eq!(storage.bits, 0);
+        assert!(storage.data.is_empty());
+        assert_eq!(storage.palette, vec![Some(stone)]);
+        assert_eq!(storage, ChunkStorage::filled(Some(stone)));
+        assert_eq!(storage.len(), CHUNK_VOLUME);
+
+        for index in 0..CHUNK_VOLUME {
+            storage.remove(&local_pos(index));
+        }
+        assert_eq!(storage.bits, 0);
+        assert_eq!(storage.palette, vec![None]);
+        assert!(storage.is_empty());
+        assert_eq!(storage, ChunkStorage::default());
+    }
+
+    #[test]
+    fn iter_matches_len() {
+        let mut storage = ChunkStorage::filled(Some(block(0)));
+        assert_eq!(storage.iter().count(), CHUNK_VOLUME);
+
+        // Air and a few states, spread over the chunk
+        for index in (0..CHUNK_VOLUME).step_by(7) {
+            let state = (index % 5) as u8;
+            storage.set(&local_pos(index), (state > 0).then(|| block(state)));
+        }
+
+        assert_eq!(storage.iter().count(), storage.len());
+        for (pos, block) in storage.iter() {
+            assert_eq!(storage.get(&pos), Some(block));
+        }
+        let air = (0..CHUNK_VOLUME)
+            .filter(|index| storage.get(&local_pos(*index)).is_none())
+            .count();
+        assert_eq!(storage.len() + air, CHUNK_VOLUME);
+        assert!(storage.contains_key(&local_pos(1)));
+        assert!(!storage.contains_key(&local_pos(0)));
+    }
+
+    #[test]
+    fn bincode_round_trip() {
+        let mut storage = ChunkStorage::default();
+        for index in (0..CHUNK_VOLUME).step_by(3) {
+            storage.set(&local_pos(index), Some(block((index % 20) as u8)));
+        }
+
+        for storage in [
+            storage,
+            ChunkStorage::default(),
+            ChunkStorage::filled(Some(block(4))),
+        ] {
+            let bytes = bincode::serialize(&storage).unwrap();
+            let decoded: ChunkStorage = bincode::deserialize(&bytes).unwrap();
+            assert_eq!(decoded, storage);
+            assert_eq!(decoded.len(), storage.len());
+            assert_eq!(decoded.counts, storage.counts);
+        }
+    }
+
+    fn decode(
+        palette: &[Option<BlockData>],
+        bits: u8,
+        data: &[u64],
+    ) -> bincode::Result<ChunkStorage> {
+        let bytes = bincode::serialize(&PackedChunkRef {
+            palette,
+            bits,
+            data,
+        })
+        .unwrap();
+        bincode::deserialize(&bytes)
+    }
+
+    #[test]
+    fn rejects_invalid_data() {
+        let palette = [None, Some(block(1)), Some(block(2))];
+
+        assert!(decode(&palette, 2, &vec![0; data_len(2)]).is_ok());
+        // Empty palette
+        assert!(decode(&[], 0, &[]).is_err());
+        // Not enough bits for the palette
+        assert!(decode(&palette, 1, &vec![0; data_len(1)]).is_err());
+        // Too many bits
+        assert!(decode(&palette, 17, &vec![0; data_len(17)]).is_err());
+        // Wrong data length
+        assert!(decode(&palette, 2, &vec![0; data_len(2) - 1]).is_err());
+        assert!(decode(&palette, 2, &vec![0; data_len(2) + 1]).is_err());
+        // Index out of the palette
+        let mut data = vec![0; data_len(2)];
+        data[10] = 3;
+        assert!(decode(&palette, 2, &data).is_err());
+    }
+}